@@ -0,0 +1,35 @@
+use crate::HashAlgo;
+use md5::{Digest, Md5};
+
+/// Incremental digest for one of the [`HashAlgo`]s this build can compute.
+pub(crate) enum Hasher {
+    Md5(Md5),
+}
+
+impl Hasher {
+    /// `None` if the algorithm isn't supported.
+    pub(crate) fn new(algo: HashAlgo) -> Option<Hasher> {
+        match algo {
+            HashAlgo::Md5 => Some(Hasher::Md5(Md5::new())),
+            _ => None,
+        }
+    }
+
+    /// Hasher matching the expected hash, `None` if it can't be verified.
+    pub(crate) fn for_expected(hash: &str) -> Option<Hasher> {
+        HashAlgo::from_hex_len(hash).and_then(Hasher::new)
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hash) => hash.update(data),
+        }
+    }
+
+    /// Lowercase hex digest.
+    pub(crate) fn finalize(self) -> String {
+        match self {
+            Hasher::Md5(hash) => hex::encode(hash.finalize()),
+        }
+    }
+}
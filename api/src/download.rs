@@ -0,0 +1,842 @@
+//! Downloading build files to disk.
+//!
+//! Files are written to `<name>.partial` next to the destination and only renamed into place
+//! once the transfer completed and the size and checksum matched, so a file at the final path
+//! is always complete. Small files can be kept in memory instead with [`download_to_bytes`].
+
+use crate::checksum::Hasher;
+use crate::{ApiError, BuildUrl, HashAlgo, Kind};
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use reqwest::{header, Client as ReqwestClient, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt, BufWriter};
+use tokio::sync::watch;
+
+/// Called with the number of bytes received after every chunk.
+pub type ProgressFn = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// How [`download_build_to`] transfers a file.
+#[derive(Clone)]
+pub struct TransferOptions {
+    /// Capacity of the write buffer in bytes.
+    pub buffer_size: usize,
+    /// Limit of the average speed in bytes per second.
+    pub max_speed: Option<u64>,
+    /// Parallel range requests, only used if the server supports them.
+    pub connections: u16,
+    /// How often a dropped connection is resumed before giving up, counted for each of the
+    /// parallel ranges on its own.
+    pub retries: u32,
+    /// Keep the partial file of a failed or interrupted transfer, with a `<filename>.progress`
+    /// file recording how much of it is valid, and continue it on the next call. Only used for
+    /// single connection transfers, parallel ones start over.
+    pub persist_progress: bool,
+    /// Hash the downloaded bytes to compare them with [`BuildUrl::hash`]. Without it only the
+    /// size is checked, which saves the hashing time on multi-gigabyte files but lets a
+    /// corrupted transfer through.
+    pub verify_hash: bool,
+    /// While `true` the transfer stops reading, and continues once it's `false` again.
+    pub paused: Option<watch::Receiver<bool>>,
+    pub on_progress: Option<ProgressFn>,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            buffer_size: 1024 * 1024,
+            max_speed: None,
+            connections: 1,
+            retries: 3,
+            persist_progress: false,
+            verify_hash: true,
+            paused: None,
+            on_progress: None,
+        }
+    }
+}
+
+impl TransferOptions {
+    fn progress(&self, bytes: usize) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(bytes as u64);
+        }
+    }
+
+    /// Return once [`TransferOptions::paused`] is unset, with how long that took.
+    async fn unpaused(&self) -> Duration {
+        let started = Instant::now();
+        if let Some(paused) = self.paused.as_ref().filter(|paused| *paused.borrow()) {
+            // Without a sender the value can't change anymore, carry on
+            let _ = paused.clone().wait_for(|paused| !paused).await;
+        }
+        started.elapsed()
+    }
+}
+
+/// A completed transfer and how it compares to the [`BuildUrl`]. A file that doesn't match is
+/// discarded, but that's not an error: the caller decides how to report it.
+#[derive(Debug)]
+pub struct DownloadOutcome {
+    /// Where the file is saved if it's [accepted](DownloadOutcome::is_accepted).
+    pub path: PathBuf,
+    pub bytes_written: u64,
+    /// Hex digest of the downloaded bytes, `None` if the algorithm isn't supported.
+    pub computed_hash: Option<String>,
+    /// [`BuildUrl::hash`].
+    pub expected_hash: String,
+    /// `false` if the hashes differ or the hash couldn't be computed.
+    pub hash_matched: bool,
+    /// [`BuildUrl::size`].
+    pub expected_size: u64,
+    /// Whether as many bytes as [`BuildUrl::size`] were written. Always true if the size is 0.
+    pub size_matched: bool,
+}
+
+impl DownloadOutcome {
+    fn new(
+        url: &BuildUrl,
+        path: PathBuf,
+        bytes_written: u64,
+        computed_hash: Option<String>,
+    ) -> Self {
+        DownloadOutcome {
+            path,
+            bytes_written,
+            hash_matched: computed_hash
+                .as_ref()
+                .is_some_and(|hash| hash.eq_ignore_ascii_case(&url.hash)),
+            computed_hash,
+            expected_hash: url.hash.clone(),
+            expected_size: url.size,
+            size_matched: url.size == 0 || bytes_written == url.size,
+        }
+    }
+
+    /// Whether the file was kept: the size matched and the hash matched or can't be computed.
+    pub fn is_accepted(&self) -> bool {
+        self.size_matched && (self.hash_matched || self.computed_hash.is_none())
+    }
+
+    /// A [`Kind::Checksum`] error (with a [`ChecksumMismatch`] source) if the hash didn't match
+    /// or a [`Kind::Request`] error if the size didn't, for callers that treat it as a failure.
+    pub fn into_result(self) -> Result<Self, ApiError> {
+        match &self.computed_hash {
+            Some(computed) if !self.hash_matched => Err(ApiError::with_kind(
+                Kind::Checksum,
+                ChecksumMismatch {
+                    expected: self.expected_hash.clone(),
+                    computed: computed.clone(),
+                },
+            )),
+            _ if !self.size_matched => Err(ApiError::with_kind(
+                Kind::Request,
+                format!(
+                    "expected {} bytes, downloaded {}",
+                    self.expected_size, self.bytes_written
+                ),
+            )),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// Source of a [`Kind::Checksum`] error.
+#[derive(Debug)]
+pub struct ChecksumMismatch {
+    pub expected: String,
+    pub computed: String,
+}
+
+impl std::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected hash {}, computed {}",
+            self.expected, self.computed
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+/// Download `url` into `dir`.
+///
+/// The data goes to `<dir>/<filename>.partial` first, which is renamed to `<dir>/<filename>`
+/// if the outcome is [accepted](DownloadOutcome::is_accepted) and removed otherwise or if the
+/// transfer fails. Hashes of an unsupported [`crate::HashAlgo`] are not verified.
+///
+/// Dropping the future cancels the download and removes the partial file too,
+/// so it can be raced against a cancellation signal:
+///
+/// ```no_run
+/// use houdini_downloader_api::download::{self, TransferOptions};
+/// use houdini_downloader_api::{ApiError, BuildUrl};
+/// use std::path::Path;
+/// use tokio::sync::oneshot;
+///
+/// async fn fetch(url: &BuildUrl, cancel: oneshot::Receiver<()>) -> Result<(), ApiError> {
+///     let (http, opts) = (reqwest::Client::new(), TransferOptions::default());
+///     tokio::select! {
+///         outcome = download::download_build_to(&http, url, Path::new("."), &opts) => {
+///             println!("{}", outcome?.path.display());
+///         }
+///         _ = cancel => println!("Cancelled"),
+///     }
+///     Ok(())
+/// }
+/// ```
+///
+/// A response that is a web page rather than the file, like an error page sent with 200 OK,
+/// fails before anything is written.
+pub async fn download_build_to(
+    http: &ReqwestClient,
+    url: &BuildUrl,
+    dir: &Path,
+    opts: &TransferOptions,
+) -> Result<DownloadOutcome, ApiError> {
+    let (partial, output) = partial_path(url, dir)?;
+    let progress = opts
+        .persist_progress
+        .then(|| partial.with_extension("progress"));
+    // Also cleans up when the future is dropped mid-transfer, unless it's continued later
+    let mut guard = RemoveOnDrop(Some(partial.as_path()).filter(|_| progress.is_none()));
+    let (bytes_written, computed_hash) =
+        transfer(http, url, &partial, progress.as_deref(), opts).await?;
+    if let Some(progress) = &progress {
+        let _ = tokio::fs::remove_file(progress).await;
+        guard.0 = Some(&partial);
+    }
+    let outcome = DownloadOutcome::new(url, output, bytes_written, computed_hash);
+    if outcome.is_accepted() {
+        tokio::fs::rename(&partial, &outcome.path).await?;
+        guard.0 = None;
+    }
+    Ok(outcome)
+}
+
+/// Where [`download_build_to`] writes `url` in `dir` until it's verified,
+/// and the final path it's renamed to.
+pub fn partial_path(url: &BuildUrl, dir: &Path) -> Result<(PathBuf, PathBuf), ApiError> {
+    let filename = sanitize_filename(&url.filename)?;
+    Ok((dir.join(format!("{filename}.partial")), dir.join(filename)))
+}
+
+/// Verify a partial file written by other means, e.g. an external downloader, and rename it
+/// into place or remove it like [`download_build_to`] does.
+pub async fn finish_partial(url: &BuildUrl, dir: &Path) -> Result<DownloadOutcome, ApiError> {
+    let (partial, output) = partial_path(url, dir)?;
+    let mut guard = RemoveOnDrop(Some(&partial));
+    let bytes_written = tokio::fs::metadata(&partial).await?.len();
+    let computed_hash = match Hasher::for_expected(&url.hash) {
+        Some(hasher) => Some(digest_file(&partial, hasher).await?.finalize()),
+        None => None,
+    };
+    let outcome = DownloadOutcome::new(url, output, bytes_written, computed_hash);
+    if outcome.is_accepted() {
+        tokio::fs::rename(&partial, &outcome.path).await?;
+        guard.0 = None;
+    }
+    Ok(outcome)
+}
+
+/// Download `url` into memory, returning the content and its hex digest, with the algorithm of
+/// [`BuildUrl::hash`] or md5 if that one isn't supported. Like [`DownloadOutcome`], comparing
+/// them with the [`BuildUrl`] is up to the caller. Unlike [`download_build_to`] a JSON or XML
+/// response is accepted, small artifacts are often just that.
+///
+/// A file larger than `max_in_memory` bytes fails with a [`Kind::Request`] error, checked
+/// against [`BuildUrl::size`] and `Content-Length` before the transfer and while receiving.
+pub async fn download_to_bytes(
+    http: &ReqwestClient,
+    url: &BuildUrl,
+    max_in_memory: u64,
+) -> Result<(Bytes, String), ApiError> {
+    let too_large = || {
+        ApiError::with_kind(
+            Kind::Request,
+            format!(
+                "{} is more than {max_in_memory} bytes, download it to a file instead",
+                url.filename
+            ),
+        )
+    };
+    if url.size > max_in_memory {
+        return Err(too_large());
+    }
+    let response = http.get(&url.download_url).send().await?;
+    check_url_expired(response.status())?;
+    let response = response.error_for_status()?;
+    let expected = response.content_length().unwrap_or(url.size);
+    if expected > max_in_memory {
+        return Err(too_large());
+    }
+    let mut hasher = Hasher::for_expected(&url.hash)
+        .or_else(|| Hasher::new(HashAlgo::Md5))
+        .expect("md5 is always supported");
+    let mut content = BytesMut::with_capacity(expected as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let bytes = chunk.map_err(|e| stream_error(e, content.len() as u64, expected))?;
+        if (content.len() + bytes.len()) as u64 > max_in_memory {
+            return Err(too_large());
+        }
+        hasher.update(&bytes);
+        content.extend_from_slice(&bytes);
+    }
+    Ok((content.freeze(), hasher.finalize()))
+}
+
+/// Removes the file when dropped, unless the path was taken out.
+struct RemoveOnDrop<'a>(Option<&'a Path>);
+
+impl Drop for RemoveOnDrop<'_> {
+    fn drop(&mut self) {
+        if let Some(path) = self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Bytes written and the hex digest, if the hash algorithm is supported.
+async fn transfer(
+    http: &ReqwestClient,
+    url: &BuildUrl,
+    output: &Path,
+    progress: Option<&Path>,
+    opts: &TransferOptions,
+) -> Result<(u64, Option<String>), ApiError> {
+    let chunked = progress.is_none()
+        && opts.connections > 1
+        && url.size > 0
+        && supports_ranges(http, &url.download_url).await;
+    let hasher = if opts.verify_hash {
+        Hasher::for_expected(&url.hash)
+    } else {
+        None
+    };
+    if chunked {
+        download_chunked(http, output, url, opts).await?;
+        let digest = match hasher {
+            Some(hasher) => Some(digest_file(output, hasher).await?.finalize()),
+            None => None,
+        };
+        Ok((url.size, digest))
+    } else {
+        download_single(http, url, output, progress, opts, hasher).await
+    }
+}
+
+/// How much of a partial file was written by an earlier run, see
+/// [`TransferOptions::persist_progress`].
+#[derive(Serialize, Deserialize)]
+struct Progress {
+    bytes: u64,
+    size: u64,
+    hash: String,
+}
+
+impl Progress {
+    /// Bytes written between two saves, so a resumed transfer repeats at most this much.
+    const INTERVAL: u64 = 64 * 1024 * 1024;
+
+    fn save(path: &Path, url: &BuildUrl, bytes: u64) -> Result<(), ApiError> {
+        let progress = Progress {
+            bytes,
+            size: url.size,
+            hash: url.hash.clone(),
+        };
+        crate::write_atomic(path, &serde_json::to_vec(&progress)?)?;
+        Ok(())
+    }
+}
+
+/// Open the partial file of an earlier run to continue it, truncated to the recorded size and
+/// with its bytes fed to the hasher. `None` if there is nothing to continue, also when the
+/// build changed since: a different size or hash discards the partial and progress files.
+async fn resume_partial(
+    output: &Path,
+    progress: &Path,
+    url: &BuildUrl,
+    hasher: &mut Option<Hasher>,
+) -> Result<Option<(tokio::fs::File, u64)>, ApiError> {
+    let Some(saved) = std::fs::read(progress)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<Progress>(&data).ok())
+    else {
+        return Ok(None);
+    };
+    let len = tokio::fs::metadata(output).await.map_or(0, |m| m.len());
+    let current = saved.size == url.size && saved.hash.eq_ignore_ascii_case(&url.hash);
+    // A complete file can't be continued with a range request, fetch it again
+    if !current || saved.bytes == 0 || saved.bytes > len || saved.bytes >= url.size {
+        let _ = tokio::fs::remove_file(progress).await;
+        return Ok(None);
+    }
+    // Bytes after the recorded size may not have been flushed completely
+    let mut file = tokio::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(output)
+        .await?;
+    file.set_len(saved.bytes).await?;
+    if let Some(partial) = hasher.take() {
+        *hasher = Some(digest_file(output, partial).await?);
+    }
+    file.seek(SeekFrom::Start(saved.bytes)).await?;
+    Ok(Some((file, saved.bytes)))
+}
+
+/// Stream the whole file over one connection, hashing on the fly.
+/// Returns the bytes written and the hex digest.
+///
+/// If the connection drops, the transfer is resumed from the last received byte with a range
+/// request, up to `opts.retries` times. A server that ignores the range gets the file
+/// restarted from the beginning; progress is reported again for the repeated bytes.
+///
+/// With a `progress` file, the partial file of an earlier run is continued and the progress
+/// is saved every [`Progress::INTERVAL`] bytes and when the transfer fails.
+async fn download_single(
+    http: &ReqwestClient,
+    url: &BuildUrl,
+    output: &Path,
+    progress: Option<&Path>,
+    opts: &TransferOptions,
+    mut hasher: Option<Hasher>,
+) -> Result<(u64, Option<String>), ApiError> {
+    let resumed = match progress {
+        Some(progress) => resume_partial(output, progress, url, &mut hasher).await?,
+        None => None,
+    };
+    let (file, mut received) = match resumed {
+        Some((file, received)) => {
+            opts.progress(received as usize);
+            (file, received)
+        }
+        None => (tokio::fs::File::create(output).await?, 0),
+    };
+    let mut file_buf = BufWriter::with_capacity(opts.buffer_size, file);
+    let mut throttle = Throttle::new(opts.max_speed);
+    let mut saved = received;
+    let mut attempt = 0;
+    let written = loop {
+        let result = async {
+            let mut request = http.get(&url.download_url);
+            if received > 0 {
+                request = request.header(header::RANGE, format!("bytes={received}-"));
+            }
+            let response = request.send().await.map_err(Interrupted::from)?;
+            check_url_expired(response.status()).map_err(Interrupted::Fatal)?;
+            let response = response
+                .error_for_status()
+                .map_err(|e| Interrupted::Fatal(e.into()))?;
+            check_content_type(&response).map_err(Interrupted::Fatal)?;
+            if received > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+                // The range was ignored and the whole file comes again, start over
+                // instead of appending it to what was already received
+                let restarted = async {
+                    file_buf.flush().await?;
+                    file_buf.get_mut().set_len(0).await?;
+                    file_buf.get_mut().seek(SeekFrom::Start(0)).await
+                };
+                restarted.await.map_err(|e| Interrupted::Fatal(e.into()))?;
+                if hasher.is_some() {
+                    hasher = Hasher::for_expected(&url.hash);
+                }
+                received = 0;
+            }
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let bytes = chunk
+                    .map_err(|e| Interrupted::Connection(stream_error(e, received, url.size)))?;
+                file_buf
+                    .write_all(&bytes)
+                    .await
+                    .map_err(|e| Interrupted::Fatal(e.into()))?;
+                if let Some(hasher) = &mut hasher {
+                    hasher.update(&bytes);
+                }
+                received += bytes.len() as u64;
+                opts.progress(bytes.len());
+                throttle.consumed(bytes.len()).await;
+                throttle.paused(opts.unpaused().await);
+                if let Some(progress) = progress.filter(|_| received - saved >= Progress::INTERVAL)
+                {
+                    // Only what's on disk may be recorded
+                    let flushed = file_buf.flush().await.map_err(ApiError::from);
+                    flushed
+                        .and_then(|()| Progress::save(progress, url, received))
+                        .map_err(Interrupted::Fatal)?;
+                    saved = received;
+                }
+            }
+            if received < url.size {
+                return Err(Interrupted::Connection(ApiError::with_kind(
+                    Kind::Request,
+                    format!("connection closed after {received} of {} bytes", url.size),
+                )));
+            }
+            Ok(())
+        }
+        .await;
+        match result {
+            Err(Interrupted::Connection(_)) if attempt < opts.retries => {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            }
+            Err(Interrupted::Connection(e) | Interrupted::Fatal(e)) => break Err(e),
+            Ok(()) => break Ok(()),
+        }
+    };
+    // Flush whatever made it into the buffer, even if the transfer failed
+    let flushed = file_buf.flush().await;
+    if let Some(progress) = progress.filter(|_| written.is_err() && flushed.is_ok()) {
+        let _ = Progress::save(progress, url, received);
+    }
+    written?;
+    flushed?;
+    Ok((received, hasher.map(Hasher::finalize)))
+}
+
+/// Error for a response body that failed after `received` of `expected` bytes.
+fn stream_error(e: reqwest::Error, received: u64, expected: u64) -> ApiError {
+    ApiError::with_kind(
+        Kind::Request,
+        format!("download interrupted after {received} of {expected} bytes: {e}"),
+    )
+}
+
+/// The CDN answers an expired pre-signed URL with 403 Forbidden (or 410 Gone),
+/// reported as [`Kind::UrlExpired`] so the caller knows to resolve the build again.
+fn check_url_expired(status: StatusCode) -> Result<(), ApiError> {
+    if status == StatusCode::FORBIDDEN || status == StatusCode::GONE {
+        return Err(ApiError::with_kind(
+            Kind::UrlExpired,
+            format!("download URL rejected with {status}, it may have expired"),
+        ));
+    }
+    Ok(())
+}
+
+/// Builds are archives and installers, so a page or a structured document is an error
+/// response, e.g. a login or expired link page sent with 200 OK, and not worth saving.
+/// A missing or unfamiliar content type is let through.
+fn check_content_type(response: &reqwest::Response) -> Result<(), ApiError> {
+    const NOT_A_BUILD: [&str; 5] = [
+        "text/html",
+        "application/xhtml+xml",
+        "application/json",
+        "application/xml",
+        "text/xml",
+    ];
+    let Some(content_type) = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Ok(());
+    };
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    if NOT_A_BUILD.iter().any(|t| mime.eq_ignore_ascii_case(t)) {
+        return Err(ApiError::with_kind(
+            Kind::Request,
+            format!("server sent {mime} instead of the file, likely an error page"),
+        ));
+    }
+    Ok(())
+}
+
+/// Why a transfer attempt stopped.
+enum Interrupted {
+    /// The connection failed or closed early, worth resuming.
+    Connection(ApiError),
+    Fatal(ApiError),
+}
+
+impl From<reqwest::Error> for Interrupted {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_status() || e.is_builder() || e.is_redirect() {
+            Interrupted::Fatal(e.into())
+        } else {
+            Interrupted::Connection(e.into())
+        }
+    }
+}
+
+/// Download disjoint byte ranges over `opts.connections` parallel requests,
+/// each writing at its own offset of the preallocated output file. A range whose connection
+/// drops is resumed from its last received byte, up to `opts.retries` times.
+async fn download_chunked(
+    http: &ReqwestClient,
+    output: &Path,
+    url: &BuildUrl,
+    opts: &TransferOptions,
+) -> Result<(), ApiError> {
+    let size = url.size;
+    let file = tokio::fs::File::create(output).await?;
+    file.set_len(size).await?;
+    drop(file);
+
+    let connections = opts.connections as u64;
+    let chunk = size.div_ceil(connections);
+    let max_speed = opts.max_speed.map(|speed| (speed / connections).max(1));
+    let ranges = (0..connections)
+        .map(|i| (i * chunk, ((i + 1) * chunk).min(size)))
+        .filter(|(start, end)| start < end);
+    futures_util::future::try_join_all(ranges.map(|(start, end)| async move {
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(output)
+            .await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        // Written in order, so the file position follows `received` across attempts
+        let mut file_buf = BufWriter::with_capacity(opts.buffer_size, file);
+        let mut throttle = Throttle::new(max_speed);
+        let len = end - start;
+        let mut received = 0;
+        let mut attempt = 0;
+        loop {
+            let result = async {
+                let response = http
+                    .get(&url.download_url)
+                    .header(
+                        header::RANGE,
+                        format!("bytes={}-{}", start + received, end - 1),
+                    )
+                    .send()
+                    .await
+                    .map_err(Interrupted::from)?;
+                check_url_expired(response.status()).map_err(Interrupted::Fatal)?;
+                check_content_type(&response).map_err(Interrupted::Fatal)?;
+                if response.status() != StatusCode::PARTIAL_CONTENT {
+                    return Err(Interrupted::Fatal(ApiError::with_kind(
+                        Kind::Request,
+                        format!(
+                            "server did not honor the range request: {}",
+                            response.status()
+                        ),
+                    )));
+                }
+                let mut stream = response.bytes_stream();
+                while let Some(chunk) = stream.next().await {
+                    let bytes = chunk
+                        .map_err(|e| Interrupted::Connection(stream_error(e, received, len)))?;
+                    if received + bytes.len() as u64 > len {
+                        return Err(Interrupted::Fatal(ApiError::with_kind(
+                            Kind::Request,
+                            format!("server sent more data than requested for bytes {start}-{end}"),
+                        )));
+                    }
+                    file_buf
+                        .write_all(&bytes)
+                        .await
+                        .map_err(|e| Interrupted::Fatal(e.into()))?;
+                    received += bytes.len() as u64;
+                    opts.progress(bytes.len());
+                    throttle.consumed(bytes.len()).await;
+                    throttle.paused(opts.unpaused().await);
+                }
+                if received < len {
+                    return Err(Interrupted::Connection(ApiError::with_kind(
+                        Kind::Request,
+                        format!("connection closed after {received} of {len} bytes"),
+                    )));
+                }
+                Ok(())
+            }
+            .await;
+            match result {
+                Err(Interrupted::Connection(_)) if attempt < opts.retries => {
+                    attempt += 1;
+                    tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                }
+                Err(Interrupted::Connection(e) | Interrupted::Fatal(e)) => return Err(e),
+                Ok(()) => break,
+            }
+        }
+        file_buf.flush().await?;
+        Ok(())
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Whether the server answers a one byte range request for the build with 206 Partial Content,
+/// so an interrupted download can continue where it stopped instead of starting over.
+/// An `Accept-Ranges` header alone isn't trusted, some servers send it and still ignore ranges.
+pub async fn is_resumable(http: &ReqwestClient, url: &BuildUrl) -> bool {
+    supports_ranges(http, &url.download_url).await
+}
+
+/// Whether the server answers a one byte range request with 206 Partial Content.
+async fn supports_ranges(http: &ReqwestClient, url: &str) -> bool {
+    http.get(url)
+        .header(header::RANGE, "bytes=0-0")
+        .send()
+        .await
+        .is_ok_and(|resp| resp.status() == StatusCode::PARTIAL_CONTENT)
+}
+
+/// Size of the file at `url` according to the server, for a [`BuildUrl`] without one: the
+/// total of a one byte range request, or the `Content-Length` of a server that ignores the
+/// range. The body isn't read. `None` if the request fails or the server doesn't say.
+pub async fn remote_size(http: &ReqwestClient, url: &BuildUrl) -> Option<u64> {
+    let response = http
+        .get(&url.download_url)
+        .header(header::RANGE, "bytes=0-0")
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return response.content_length().filter(|len| *len > 0);
+    }
+    // bytes 0-0/<total>
+    response
+        .headers()
+        .get(header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+        .filter(|len| *len > 0)
+}
+
+/// Whether the file at `path` has the size and hash of `url`. The size is compared first, so
+/// a file of the wrong size is rejected without being read. An unknown size isn't compared.
+/// `false` if the hash algorithm isn't supported, since the file can't be verified.
+pub async fn verify_file(path: &Path, url: &BuildUrl) -> Result<bool, ApiError> {
+    if url.size > 0 && tokio::fs::metadata(path).await?.len() != url.size {
+        return Ok(false);
+    }
+    let Some(hasher) = Hasher::for_expected(&url.hash) else {
+        return Ok(false);
+    };
+    Ok(digest_file(path, hasher)
+        .await?
+        .finalize()
+        .eq_ignore_ascii_case(&url.hash))
+}
+
+/// Lowercase hex digest of the file at `path`, a [`Kind::Checksum`] error if the algorithm
+/// isn't supported. The file is read on a blocking thread, so hashing a large file doesn't
+/// stall the async runtime:
+///
+/// ```
+/// use houdini_downloader_api::{download, HashAlgo};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let path = std::env::temp_dir().join("houdini-hash-example.txt");
+/// std::fs::write(&path, "houdini").unwrap();
+/// let digest = download::hash_file(&path, HashAlgo::Md5).await.unwrap();
+/// assert_eq!(digest, "efdc8aa869642809b0f0132706218f0a");
+/// # std::fs::remove_file(path).unwrap();
+/// # }
+/// ```
+pub async fn hash_file(path: &Path, algo: HashAlgo) -> Result<String, ApiError> {
+    let Some(hasher) = Hasher::new(algo) else {
+        return Err(ApiError::with_kind(
+            Kind::Checksum,
+            format!("{algo} hashes are not supported"),
+        ));
+    };
+    Ok(digest_file(path, hasher).await?.finalize())
+}
+
+/// Feed the file at `path` to the hasher in chunks, on a blocking thread.
+async fn digest_file(path: &Path, mut hash: Hasher) -> Result<Hasher, ApiError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(path)?;
+        let mut buf = vec![0; 1024 * 1024];
+        loop {
+            let read = std::io::Read::read(&mut file, &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hash.update(&buf[..read]);
+        }
+        Ok(hash)
+    })
+    .await
+    .map_err(|e| ApiError::with_kind(Kind::Io, e))?
+}
+
+/// Keeps the average transfer speed under a limit by sleeping between chunks.
+struct Throttle {
+    max_speed: Option<u64>,
+    started: Instant,
+    consumed: u64,
+}
+
+impl Throttle {
+    fn new(max_speed: Option<u64>) -> Self {
+        Throttle {
+            max_speed,
+            started: Instant::now(),
+            consumed: 0,
+        }
+    }
+
+    async fn consumed(&mut self, bytes: usize) {
+        let Some(max_speed) = self.max_speed else {
+            return;
+        };
+        self.consumed += bytes as u64;
+        // Sleep until the average speed drops back to the limit
+        let expected = Duration::from_secs_f64(self.consumed as f64 / max_speed as f64);
+        if let Some(ahead) = expected.checked_sub(self.started.elapsed()) {
+            tokio::time::sleep(ahead).await;
+        }
+    }
+
+    /// Leave time spent paused out of the average, so resuming doesn't burst past the limit.
+    fn paused(&mut self, duration: Duration) {
+        self.started += duration;
+    }
+}
+
+/// Make the server-provided filename safe to join onto the output directory.
+/// Only the last path segment is kept and, on Windows, reserved characters are replaced.
+///
+/// ```
+/// use houdini_downloader_api::download::sanitize_filename;
+///
+/// assert_eq!(sanitize_filename("../../houdini.tar.gz").unwrap(), "houdini.tar.gz");
+/// assert!(sanitize_filename("..").is_err());
+/// ```
+pub fn sanitize_filename(filename: &str) -> Result<String, ApiError> {
+    let name = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let name: String = if cfg!(windows) {
+        name.chars()
+            .map(|c| match c {
+                '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect::<String>()
+            .trim_end_matches(['.', ' '])
+            .to_string()
+    } else {
+        name.to_string()
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(ApiError::with_kind(
+            Kind::Decode,
+            format!("server returned an invalid filename: {filename:?}"),
+        ));
+    }
+    Ok(name)
+}
@@ -1,48 +1,145 @@
+mod checksum;
+pub mod download;
+mod listing_cache;
+pub mod netrc;
+mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+
 use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use listing_cache::CachedListing;
+use reqwest::header::HeaderMap;
 use reqwest::Client as ReqwestClient;
 use reqwest::StatusCode;
+use serde::ser::SerializeStruct;
 use serde::{de::Error, Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
 use std::error::Error as StdError;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{RwLock, Semaphore};
 
 const ACCESS_TOKEN_URL: &str = "https://www.sidefx.com/oauth2/application_token";
 const ENDPOINT_URL: &str = "https://www.sidefx.com/api";
 
+/// `User-Agent` sent by default, so SideFX can identify traffic from this tool.
+pub const DEFAULT_USER_AGENT: &str = concat!("houdini-downloader/", env!("CARGO_PKG_VERSION"));
+
 pub(crate) type BoxError = Box<dyn StdError + Send + Sync>;
 
+/// Category of an [`ApiError`].
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// Sending the request or receiving the response failed.
+    Request,
+    /// SideFX rejected the credentials.
+    Auth,
+    /// The response could not be decoded.
+    Decode,
+    /// Reading or writing a local file failed.
+    Io,
+    /// A downloaded file did not match its expected checksum.
+    Checksum,
+    /// The download URL was rejected, usually because the pre-signed URL expired.
+    /// Resolving the build again gives a fresh one.
+    UrlExpired,
+    /// SideFX has no such build for the platform.
+    NotFound,
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Kind::Request => "request",
+            Kind::Auth => "auth",
+            Kind::Decode => "decode",
+            Kind::Io => "io",
+            Kind::Checksum => "checksum",
+            Kind::UrlExpired => "url expired",
+            Kind::NotFound => "not found",
+        })
+    }
+}
+
 #[derive(Debug)]
-pub struct ApiError(BoxError);
+pub struct ApiError {
+    kind: Kind,
+    source: BoxError,
+}
 
 impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("ApiError: {}", self.0))
+        write!(f, "ApiError ({}): {}", self.kind, self.source)
     }
 }
 
-impl StdError for ApiError {}
+impl StdError for ApiError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
 
 impl ApiError {
-    pub(crate) fn new<E>(source: E) -> ApiError
+    pub(crate) fn with_kind<E>(kind: Kind, source: E) -> ApiError
     where
         E: Into<BoxError>,
     {
-        ApiError(source.into())
+        ApiError {
+            kind,
+            source: source.into(),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
     }
 }
+
 impl From<reqwest::Error> for ApiError {
     fn from(value: reqwest::Error) -> Self {
-        ApiError(Box::new(value))
+        let kind = if value.is_decode() {
+            Kind::Decode
+        } else {
+            Kind::Request
+        };
+        ApiError::with_kind(kind, value)
     }
 }
 
 impl From<serde_json::Error> for ApiError {
     fn from(value: serde_json::Error) -> Self {
-        ApiError::new(Box::new(value))
+        ApiError::with_kind(Kind::Decode, value)
     }
 }
 
+impl From<std::io::Error> for ApiError {
+    fn from(value: std::io::Error) -> Self {
+        ApiError::with_kind(Kind::Io, value)
+    }
+}
+
+/// SideFX product. Serializes to the identifier the API expects:
+///
+/// ```
+/// use houdini_downloader_api::Product;
+///
+/// for (product, expected) in [
+///     (Product::Houdini, r#""houdini""#),
+///     (Product::HoudiniLauncher, r#""houdini-launcher""#),
+///     (Product::LauncherIso, r#""launcher-iso""#),
+/// ] {
+///     assert_eq!(serde_json::to_string(&product).unwrap(), expected);
+///     assert_eq!(serde_json::from_str::<Product>(expected).unwrap(), product);
+///     assert_eq!(format!(r#""{product}""#), expected);
+/// }
+/// ```
 #[non_exhaustive]
-#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Product {
     Houdini,
@@ -52,127 +149,1052 @@ pub enum Product {
     LauncherIso,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+/// Target platform of a build. Serializes to the identifier the API expects:
+///
+/// ```
+/// use houdini_downloader_api::Platform;
+///
+/// for (platform, expected) in [
+///     (Platform::Linux, r#""linux""#),
+///     (Platform::Win64, r#""win64""#),
+///     (Platform::Macos, r#""macos""#),
+///     (Platform::MacosxArm64, r#""macosx_arm64""#),
+///     (Platform::Other("linux_arm64".to_string()), r#""linux_arm64""#),
+/// ] {
+///     assert_eq!(serde_json::to_string(&platform).unwrap(), expected);
+///     assert_eq!(serde_json::from_str::<Platform>(expected).unwrap(), platform);
+///     assert_eq!(format!(r#""{platform}""#), expected);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Platform {
     Linux,
     Win64,
     Macos,
-    #[serde(rename = "macosx_arm64")]
     MacosxArm64,
+    /// A raw SideFX platform identifier, sent as is and not validated.
+    /// For platforms the other variants can't express.
+    Other(String),
+}
+
+impl Serialize for Platform {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_api_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Platform {
+    fn deserialize<D: serde::Deserializer<'de>>(des: D) -> Result<Self, D::Error> {
+        let platform = String::deserialize(des)?;
+        Ok(match platform.as_str() {
+            "linux" => Platform::Linux,
+            "win64" => Platform::Win64,
+            "macos" => Platform::Macos,
+            "macosx_arm64" => Platform::MacosxArm64,
+            _ => Platform::Other(platform),
+        })
+    }
+}
+
+impl Product {
+    /// Whether the product has builds per platform. The launcher ISO is one image for all of
+    /// them, so API requests for it leave the platform out.
+    pub fn requires_platform(&self) -> bool {
+        !matches!(self, Product::LauncherIso)
+    }
+
+    /// One line about the product, for listing the products to choose from.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Product::Houdini => "Houdini installers",
+            Product::HoudiniLauncher => "Houdini Launcher, which installs and manages Houdini",
+            Product::LauncherIso => "Houdini Launcher ISO image, one for all platforms",
+        }
+    }
+
+    /// The identifier SideFX uses for the product.
+    pub fn as_api_str(&self) -> &'static str {
+        match self {
+            Product::Houdini => "houdini",
+            Product::HoudiniLauncher => "houdini-launcher",
+            Product::LauncherIso => "launcher-iso",
+        }
+    }
+}
+
+impl std::fmt::Display for Product {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_api_str())
+    }
+}
+
+/// Parses the identifier SideFX uses, ignoring case, or "launcher" for the launcher:
+///
+/// ```
+/// use houdini_downloader_api::Product;
+///
+/// assert_eq!("houdini-launcher".parse::<Product>().unwrap(), Product::HoudiniLauncher);
+/// assert_eq!(Product::try_from("Launcher").unwrap(), Product::HoudiniLauncher);
+/// assert!("maya".parse::<Product>().is_err());
+/// ```
+impl std::str::FromStr for Product {
+    type Err = ParseProductError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "houdini" => Ok(Product::Houdini),
+            "houdini-launcher" | "launcher" => Ok(Product::HoudiniLauncher),
+            "launcher-iso" => Ok(Product::LauncherIso),
+            _ => Err(ParseProductError(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<&str> for Product {
+    type Error = ParseProductError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// A string that isn't a [`Product`].
+#[derive(Debug)]
+pub struct ParseProductError(String);
+
+impl std::fmt::Display for ParseProductError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown product {:?}, expected houdini, houdini-launcher or launcher-iso",
+            self.0
+        )
+    }
+}
+
+impl StdError for ParseProductError {}
+
+impl Platform {
+    pub const ALL: [Platform; 4] = [
+        Platform::Linux,
+        Platform::Win64,
+        Platform::Macos,
+        Platform::MacosxArm64,
+    ];
+
+    /// The identifier SideFX uses for the platform.
+    pub fn as_api_str(&self) -> &str {
+        match self {
+            Platform::Linux => "linux",
+            Platform::Win64 => "win64",
+            Platform::Macos => "macos",
+            Platform::MacosxArm64 => "macosx_arm64",
+            Platform::Other(platform) => platform,
+        }
+    }
+
+    /// The platform of the running OS and architecture, `None` if SideFX has no builds for it.
+    pub fn current() -> Option<Platform> {
+        if cfg!(target_os = "windows") {
+            Some(Platform::Win64)
+        } else if cfg!(target_os = "linux") {
+            Some(Platform::Linux)
+        } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+            Some(Platform::Macos)
+        } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+            Some(Platform::MacosxArm64)
+        } else {
+            None
+        }
+    }
+
+    /// Map the platform string of a listed [`Build`] (e.g. "linux_x86_64_gcc9.3") back to a `Platform`.
+    /// Platforms the other variants don't cover are kept as [`Platform::Other`]:
+    ///
+    /// ```
+    /// use houdini_downloader_api::Platform;
+    ///
+    /// assert_eq!(Platform::from_build_str("linux_x86_64_gcc9.3"), Platform::Linux);
+    /// assert_eq!(Platform::from_build_str("macosx_arm64_clang14.0_13"), Platform::MacosxArm64);
+    /// assert_eq!(
+    ///     Platform::from_build_str("linux_arm64_gcc11.2"),
+    ///     Platform::Other("linux_arm64_gcc11.2".to_string())
+    /// );
+    /// ```
+    pub fn from_build_str(platform: &str) -> Platform {
+        let is = |prefix: &str| platform == prefix || platform.starts_with(&format!("{prefix}_"));
+        if is("linux_x86_64") || platform == "linux" {
+            Platform::Linux
+        } else if platform.starts_with("win64") {
+            Platform::Win64
+        } else if is("macosx_arm64") {
+            Platform::MacosxArm64
+        } else if is("macosx_x86_64") || platform == "macos" {
+            Platform::Macos
+        } else {
+            Platform::Other(platform.to_string())
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_api_str())
+    }
+}
+
+/// Parses the identifier SideFX uses or a common name for it, ignoring case. Other platforms
+/// are an error here, [`Platform::Other`] has to be made explicitly:
+///
+/// ```
+/// use houdini_downloader_api::Platform;
+///
+/// assert_eq!("macosx_arm64".parse::<Platform>().unwrap(), Platform::MacosxArm64);
+/// assert_eq!("Windows".parse::<Platform>().unwrap(), Platform::Win64);
+/// assert_eq!(Platform::try_from("mac").unwrap(), Platform::Macos);
+/// assert!("linux_arm64".parse::<Platform>().is_err());
+/// ```
+impl std::str::FromStr for Platform {
+    type Err = ParsePlatformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "linux" => Ok(Platform::Linux),
+            "win64" | "windows" | "win" => Ok(Platform::Win64),
+            "macos" | "macosx" | "mac" | "osx" => Ok(Platform::Macos),
+            "macosx_arm64" | "macos_arm64" | "mac_arm64" => Ok(Platform::MacosxArm64),
+            _ => Err(ParsePlatformError(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<&str> for Platform {
+    type Error = ParsePlatformError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// A string that isn't one of the named [`Platform`]s.
+#[derive(Debug)]
+pub struct ParsePlatformError(String);
+
+impl std::fmt::Display for ParsePlatformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown platform {:?}, expected linux, win64, macos or macosx_arm64",
+            self.0
+        )
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl StdError for ParsePlatformError {}
+
+/// Parameters of `download.get_daily_builds_list`. The keys are pinned to the ones the API expects:
+///
+/// ```
+/// use houdini_downloader_api::{ListBuildsParms, Platform, Product};
+///
+/// let parms = ListBuildsParms::builder()
+///     .product(Product::Houdini)
+///     .platform(Platform::Linux)
+///     .only_production(true)
+///     .build();
+/// assert_eq!(
+///     serde_json::to_value(&parms).unwrap(),
+///     serde_json::json!({"product": "houdini", "platform": "linux",
+///         "version": null, "only_production": true})
+/// );
+/// ```
+///
+/// The platform is left out for products that don't [require one](Product::requires_platform):
+///
+/// ```
+/// use houdini_downloader_api::{ListBuildsParms, Product};
+///
+/// let parms = ListBuildsParms::builder().product(Product::LauncherIso).build();
+/// assert_eq!(
+///     serde_json::to_value(&parms).unwrap(),
+///     serde_json::json!({"product": "launcher-iso", "version": null, "only_production": true})
+/// );
+/// ```
+#[derive(Debug, Deserialize)]
 pub struct ListBuildsParms {
+    #[serde(rename = "product")]
     pub product: Product,
+    #[serde(rename = "platform", default = "default_platform")]
     pub platform: Platform,
     // TODO: Support version list
+    #[serde(rename = "version")]
     pub version: Option<String>,
+    #[serde(rename = "only_production")]
     pub only_production: bool,
 }
 
+impl Serialize for ListBuildsParms {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut parms = serializer.serialize_struct("ListBuildsParms", 4)?;
+        parms.serialize_field("product", &self.product)?;
+        serialize_platform(&mut parms, self.product, &self.platform)?;
+        parms.serialize_field("version", &self.version)?;
+        parms.serialize_field("only_production", &self.only_production)?;
+        parms.end()
+    }
+}
+
+/// Platform of parameters that leave it out, see [`Product::requires_platform`].
+fn default_platform() -> Platform {
+    Platform::Linux
+}
+
+fn serialize_platform<S: SerializeStruct>(
+    parms: &mut S,
+    product: Product,
+    platform: &Platform,
+) -> Result<(), S::Error> {
+    if product.requires_platform() {
+        parms.serialize_field("platform", platform)
+    } else {
+        parms.skip_field("platform")
+    }
+}
+
 impl ListBuildsParms {
     pub fn new() -> Self {
         ListBuildsParms {
             product: Product::Houdini,
-            platform: Platform::Linux,
+            platform: default_platform(),
             version: None,
             only_production: true,
         }
     }
+
+    /// Start building list parameters from the defaults of [`ListBuildsParms::new`].
+    ///
+    /// ```
+    /// use houdini_downloader_api::{ListBuildsParms, Platform, Product};
+    ///
+    /// let parms = ListBuildsParms::builder()
+    ///     .product(Product::HoudiniLauncher)
+    ///     .platform(Platform::Win64)
+    ///     .version("20.0")
+    ///     .only_production(false)
+    ///     .build();
+    /// assert_eq!(parms.version.as_deref(), Some("20.0"));
+    /// assert!(!parms.only_production);
+    /// ```
+    pub fn builder() -> ListBuildsParmsBuilder {
+        ListBuildsParmsBuilder {
+            parms: ListBuildsParms::new(),
+        }
+    }
+}
+
+impl Default for ListBuildsParms {
+    fn default() -> Self {
+        ListBuildsParms::new()
+    }
+}
+
+/// Fluent builder for [`ListBuildsParms`].
+///
+/// Unset options keep their defaults: Houdini on Linux, all versions, production builds only.
+///
+/// ```
+/// use houdini_downloader_api::ListBuildsParms;
+///
+/// let parms = ListBuildsParms::builder().build();
+/// assert!(parms.version.is_none());
+/// assert!(parms.only_production);
+/// ```
+#[derive(Debug)]
+pub struct ListBuildsParmsBuilder {
+    parms: ListBuildsParms,
+}
+
+impl ListBuildsParmsBuilder {
+    pub fn product(mut self, product: Product) -> Self {
+        self.parms.product = product;
+        self
+    }
+
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.parms.platform = platform;
+        self
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.parms.version = Some(version.into());
+        self
+    }
+
+    pub fn only_production(mut self, only_production: bool) -> Self {
+        self.parms.only_production = only_production;
+        self
+    }
+
+    pub fn build(self) -> ListBuildsParms {
+        self.parms
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Parameters of `download.get_daily_build_download`, keys pinned like [`ListBuildsParms`].
+#[derive(Debug, Deserialize)]
 struct DownloadParms {
+    #[serde(rename = "product")]
     product: Product,
+    #[serde(rename = "platform", default = "default_platform")]
     platform: Platform,
+    #[serde(rename = "version")]
     version: String,
+    #[serde(rename = "build")]
     build: u64,
 }
 
+impl Serialize for DownloadParms {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut parms = serializer.serialize_struct("DownloadParms", 4)?;
+        parms.serialize_field("product", &self.product)?;
+        serialize_platform(&mut parms, self.product, &self.platform)?;
+        parms.serialize_field("version", &self.version)?;
+        parms.serialize_field("build", &self.build)?;
+        parms.end()
+    }
+}
+
+impl DownloadParms {
+    fn for_build(build: &Build) -> Self {
+        DownloadParms {
+            product: build.product,
+            platform: Platform::from_build_str(&build.platform),
+            version: build.version.clone(),
+            build: build.build,
+        }
+    }
+}
+
 enum EndPoint {
     ListBuilds(ListBuildsParms),
     Download(DownloadParms),
 }
 
+impl EndPoint {
+    /// The `json` form field of the API call.
+    fn form(self) -> String {
+        let (method, parms) = match self {
+            EndPoint::ListBuilds(parms) => (
+                "download.get_daily_builds_list",
+                serde_json::to_value(parms).unwrap(),
+            ),
+            EndPoint::Download(parms) => (
+                "download.get_daily_build_download",
+                serde_json::to_value(parms).unwrap(),
+            ),
+        };
+        json!([method, [], parms]).to_string()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Token {
+    access_token: String,
+    // Lifespan of the token
+    expires_in: u64,
+    #[serde(default)]
+    // Time in seconds when the token expire
+    expires_at: u64,
+}
+
+/// Parse an API response, keeping the body in the error if it isn't JSON (e.g. an error page).
+fn decode_raw(body: &[u8]) -> Result<Value, ApiError> {
+    serde_json::from_slice(body)
+        .map_err(|_| ApiError::with_kind(Kind::Decode, String::from_utf8_lossy(body).into_owned()))
+}
+
+/// Whether a build URL response says there is no such build: no result at all,
+/// or an error message like `{"error": "Build 20.0.999 does not exist"}`.
+fn is_not_found(raw: &Value) -> bool {
+    let message = match raw {
+        Value::Null => return true,
+        Value::Object(fields) if fields.is_empty() => return true,
+        Value::Array(items) if items.is_empty() => return true,
+        Value::Object(fields) => ["error", "detail", "message"].iter().find_map(|key| {
+            let value = fields.get(*key)?;
+            value.as_str().or_else(|| value.get("message")?.as_str())
+        }),
+        _ => None,
+    };
+    message.is_some_and(|message| {
+        let message = message.to_lowercase();
+        ["not found", "does not exist", "no such"]
+            .iter()
+            .any(|phrase| message.contains(phrase))
+    })
+}
+
+/// Write to a temporary file next to `path` and rename it into place,
+/// so an interrupted write never leaves a truncated file behind.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+/// Log API traffic to stderr when [`ClientConfig::trace_http`] is set.
+fn trace(enabled: bool, message: impl FnOnce() -> String) {
+    if enabled {
+        eprintln!("[http] {}", message());
+    }
+}
+
+/// Longest `Retry-After` that's waited out. A longer one fails the request instead of leaving
+/// it hanging for what looks like no reason.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// The wait a `Retry-After` header asks for, in seconds or until an HTTP date, counted from
+/// `now`. A date in the past is no wait. `None` without a header or if it can't be parsed.
+///
+/// ```
+/// use houdini_downloader_api::retry_after;
+/// use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+/// use std::time::{Duration, SystemTime};
+///
+/// let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// let mut headers = HeaderMap::new();
+/// headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+/// assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(30)));
+///
+/// // 1_700_000_000 is Tue, 14 Nov 2023 22:13:20 GMT
+/// headers.insert(RETRY_AFTER, HeaderValue::from_static("Tue, 14 Nov 2023 22:14:05 GMT"));
+/// assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(45)));
+/// headers.insert(RETRY_AFTER, HeaderValue::from_static("Tue, 14 Nov 2023 22:00:00 GMT"));
+/// assert_eq!(retry_after(&headers, now), Some(Duration::ZERO));
+///
+/// headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+/// assert_eq!(retry_after(&headers, now), None);
+/// assert_eq!(retry_after(&HeaderMap::new(), now), None);
+/// ```
+pub fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// A 429 Too Many Requests response about to be retried, see [`ClientConfig::on_rate_limit`].
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    /// How long the retry waits.
+    pub wait: Duration,
+    /// Whether the wait is the server's `Retry-After` rather than the client's own backoff.
+    pub retry_after: bool,
+}
+
+/// Callback of [`ClientConfig::on_rate_limit`].
+#[derive(Clone)]
+pub struct RateLimitFn(pub Arc<dyn Fn(&RateLimited) + Send + Sync>);
+
+impl std::fmt::Debug for RateLimitFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RateLimitFn")
+    }
+}
+
+/// Wait before retry `attempt`, counting from 1, of the rate limited `resp`: as long as its
+/// `Retry-After` asks, or with exponential backoff without one. `false` without waiting if the
+/// server asks for longer than [`MAX_RETRY_AFTER`].
+async fn wait_rate_limited(config: &ClientConfig, resp: &reqwest::Response, attempt: u32) -> bool {
+    let retry_after = retry_after(resp.headers(), SystemTime::now());
+    let wait = retry_after.unwrap_or(Duration::from_secs(1 << attempt));
+    if wait > MAX_RETRY_AFTER {
+        return false;
+    }
+    if let Some(on_rate_limit) = &config.on_rate_limit {
+        (on_rate_limit.0)(&RateLimited {
+            wait,
+            retry_after: retry_after.is_some(),
+        });
+    }
+    tokio::time::sleep(wait).await;
+    true
+}
+
+fn time_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Environment variable overriding [`cache_dir`].
+pub const CACHE_DIR_ENV: &str = "HOUDINI_DOWNLOADER_CACHE_DIR";
+
+/// Where data is cached between runs: `$HOUDINI_DOWNLOADER_CACHE_DIR` if set, otherwise
+/// `houdini.downloader` in the platform cache directory, if there is one.
+pub fn cache_dir() -> Option<PathBuf> {
+    env_cache_dir().or_else(|| dirs::cache_dir().map(|path| path.join("houdini.downloader")))
+}
+
+fn env_cache_dir() -> Option<PathBuf> {
+    std::env::var_os(CACHE_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Where the access token is cached between runs by default, see [`cache_dir`].
+pub fn token_cache_file() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("api.token"))
+}
+
+/// Expiry of the cached access token in seconds since the Unix epoch,
+/// `None` if there is no readable cached token.
+pub fn cached_token_expires_at() -> Option<u64> {
+    ClientConfig::default().cached_token_expires_at()
+}
+
 async fn get_access_token(
     client: &ReqwestClient,
     user_id: &str,
     user_secret: &str,
+    config: &ClientConfig,
 ) -> Result<String, ApiError> {
-    #[derive(Deserialize, Serialize)]
-    struct Token {
-        access_token: String,
-        // Lifespan of the token
-        expires_in: u64,
-        #[serde(default)]
-        // Time in seconds when the token expire
-        expires_at: u64,
-    }
-
-    fn time_now() -> u64 {
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    }
-    let token_file = dirs::cache_dir().map(|path| {
-        path.join("houdini.downloader")
-            .join("api")
-            .with_extension("token")
-    });
-
+    let token_file = config.token_cache_file();
+    let trace_http = config.trace_http;
     if let Some(token_file) = &token_file {
         if let Ok(data) = std::fs::read(token_file) {
             let token: Token = serde_json::from_slice(&data)?;
             if time_now() < token.expires_at {
+                trace(trace_http, || {
+                    format!("using cached access token {}", token_file.display())
+                });
                 return Ok(token.access_token);
             }
         }
     }
 
-    let resp = client
-        .post(ACCESS_TOKEN_URL)
-        .basic_auth(user_id, Some(user_secret))
-        .send()
-        .await?;
+    let mut attempt = 0;
+    let body = loop {
+        trace(trace_http, || {
+            format!(
+                "POST {} (basic auth credentials redacted)",
+                config.token_url
+            )
+        });
+        let resp = with_headers(client.post(&config.token_url), &config.headers)
+            .basic_auth(user_id, Some(user_secret))
+            .send()
+            .await?;
+        trace(trace_http, || format!("status: {}", resp.status()));
 
-    if !resp.status().is_success() {
-        return match resp.status() {
-            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(ApiError::new(
-                "Could not authorize, check user credentials.".to_string(),
-            )),
-            error_status => Err(ApiError::new(format!(
-                "Request error code: {error_status:?}"
-            ))),
-        };
-    }
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS
+            && attempt < config.api_retries
+            && wait_rate_limited(config, &resp, attempt + 1).await
+        {
+            attempt += 1;
+            continue;
+        }
+        if !resp.status().is_success() {
+            return match resp.status() {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(ApiError::with_kind(
+                    Kind::Auth,
+                    "Could not authorize, check user credentials.",
+                )),
+                error_status => Err(ApiError::with_kind(
+                    Kind::Request,
+                    format!("Request error code: {error_status:?}"),
+                )),
+            };
+        }
 
-    let mut token: Token = resp.json().await?;
+        // A maintenance page comes with a 200 status, tell it apart from a token by its type
+        let is_json = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_none_or(|content_type| content_type.contains("json"));
+        let body = resp.bytes().await?;
+        if is_json {
+            break body;
+        }
+        if attempt < config.api_retries {
+            attempt += 1;
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            continue;
+        }
+        let text = String::from_utf8_lossy(&body);
+        let start: String = text.trim().chars().take(200).collect();
+        return Err(ApiError::with_kind(
+            Kind::Request,
+            format!("auth endpoint returned non-JSON (possible maintenance), body starts: {start}"),
+        ));
+    };
+    trace(trace_http, || {
+        let mut body: Value = serde_json::from_slice(&body).unwrap_or_default();
+        if let Some(token) = body.get_mut("access_token") {
+            *token = "<redacted>".into();
+        }
+        format!("response: {body}")
+    });
+    let mut token: Token = serde_json::from_slice(&body)?;
 
     if let Some(token_file) = &token_file {
         let _ = std::fs::create_dir_all(token_file.parent().expect("parent must present"));
-        if let Ok(file) = std::fs::File::create(token_file) {
-            token.expires_at = time_now() + token.expires_in;
-            if let Err(e) = serde_json::to_writer(file, &token) {
-                eprintln!("Could not save token file {}", e)
-            }
-        }
+        token.expires_at = time_now() + token.expires_in;
+        let _ = write_atomic(token_file, &serde_json::to_vec(&token)?);
     }
 
     Ok(token.access_token)
 }
 
+/// Options for the HTTP client used by [`SesiClient`].
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Value of the `User-Agent` header sent with every request.
+    pub user_agent: String,
+    /// Maximum number of API requests in flight at once, shared by all batch helpers.
+    pub max_concurrent_requests: usize,
+    /// How many times the token request or an API request is retried when SideFX responds with
+    /// 429 Too Many Requests.
+    pub api_retries: u32,
+    /// Skip TLS certificate validation. Only meant for proxies that break the certificate chain.
+    pub danger_accept_invalid_certs: bool,
+    /// Extra root certificates to trust, e.g. the one of a corporate proxy.
+    pub root_certificates: Vec<reqwest::Certificate>,
+    /// Cache directory to use instead of [`cache_dir`].
+    pub cache_dir: Option<PathBuf>,
+    /// Read and save the access token in the cache directory.
+    pub token_cache: bool,
+    /// Keep build listings in the cache directory and only download them again when SideFX
+    /// reports a change with `ETag` or `Last-Modified`.
+    pub listing_cache: bool,
+    /// Print API requests and responses to stderr, with credentials redacted.
+    pub trace_http: bool,
+    /// Redirects followed before a request fails, 0 to not follow any.
+    pub max_redirects: usize,
+    /// Idle connections kept open per host for reuse, `None` for reqwest's default of no limit.
+    /// Lower it when a proxy limits the connections per client.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept for reuse, `None` for reqwest's default of 90
+    /// seconds. Shorter than the idle timeout of a proxy, so it doesn't close them first.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Only speak HTTP/1.1, for proxies that hang or break connections with HTTP/2.
+    pub http1_only: bool,
+    /// Called before a request rate limited with 429 Too Many Requests is retried, e.g. to tell
+    /// the user why nothing happens for a while. Requests are retried
+    /// [`ClientConfig::api_retries`] times, honoring `Retry-After` up to 5 minutes:
+    ///
+    /// ```
+    /// use houdini_downloader_api::{ClientConfig, RateLimitFn};
+    /// use std::sync::Arc;
+    ///
+    /// let config = ClientConfig {
+    ///     on_rate_limit: Some(RateLimitFn(Arc::new(|limited| {
+    ///         eprintln!("Rate limited, retrying in {}s", limited.wait.as_secs());
+    ///     }))),
+    ///     ..ClientConfig::default()
+    /// };
+    /// ```
+    pub on_rate_limit: Option<RateLimitFn>,
+    /// Where the access token is requested, e.g. to go through a mirror or a test server.
+    pub token_url: String,
+    /// Where API calls are sent.
+    pub api_url: String,
+    /// Extra headers sent with the token request and every API call, e.g. for an API gateway.
+    /// Not sent with the downloads, which go to the CDN. An `Authorization` header is ignored,
+    /// the credentials set their own; a `User-Agent` header replaces [`ClientConfig::user_agent`]:
+    ///
+    /// ```
+    /// use houdini_downloader_api::ClientConfig;
+    /// use reqwest::header::{HeaderMap, HeaderValue};
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("X-Gateway-Token", HeaderValue::from_static("secret"));
+    /// let config = ClientConfig {
+    ///     headers,
+    ///     ..ClientConfig::default()
+    /// };
+    /// ```
+    pub headers: HeaderMap,
+    /// Largest file [`SesiClient::download_to_bytes`] keeps in memory, in bytes.
+    pub max_in_memory: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_concurrent_requests: 4,
+            api_retries: 3,
+            danger_accept_invalid_certs: false,
+            root_certificates: Vec::new(),
+            cache_dir: None,
+            token_cache: true,
+            listing_cache: true,
+            trace_http: false,
+            max_redirects: 10,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            http1_only: false,
+            on_rate_limit: None,
+            token_url: ACCESS_TOKEN_URL.to_string(),
+            api_url: ENDPOINT_URL.to_string(),
+            headers: HeaderMap::new(),
+            max_in_memory: 100 * 1024 * 1024,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Build an HTTP client with these options, e.g. to download a [`BuildUrl`] without a [`SesiClient`].
+    pub fn http_client(&self) -> Result<ReqwestClient, ApiError> {
+        let mut builder = ReqwestClient::builder()
+            .user_agent(&self.user_agent)
+            .danger_accept_invalid_certs(self.danger_accept_invalid_certs)
+            .redirect(match self.max_redirects {
+                0 => reqwest::redirect::Policy::none(),
+                n => reqwest::redirect::Policy::limited(n),
+            });
+        for cert in &self.root_certificates {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if self.http1_only {
+            builder = builder.http1_only();
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Token cache file with these options, `None` if the token isn't cached.
+    pub fn token_cache_file(&self) -> Option<PathBuf> {
+        if !self.token_cache {
+            return None;
+        }
+        match &self.cache_dir {
+            Some(dir) => Some(dir.join("api.token")),
+            None => token_cache_file(),
+        }
+    }
+
+    /// Directory build listings are cached in with these options, `None` if they aren't cached.
+    pub fn listing_cache_dir(&self) -> Option<PathBuf> {
+        if !self.listing_cache {
+            return None;
+        }
+        self.cache_dir.clone().or_else(cache_dir)
+    }
+
+    /// Expiry of the token in [`ClientConfig::token_cache_file`], see [`cached_token_expires_at`].
+    pub fn cached_token_expires_at(&self) -> Option<u64> {
+        let data = std::fs::read(self.token_cache_file()?).ok()?;
+        let token: Token = serde_json::from_slice(&data).ok()?;
+        Some(token.expires_at)
+    }
+
+    /// Create an explicitly configured cache directory, so a bad path is reported
+    /// instead of silently running without a cache.
+    fn create_cache_dir(&self) -> Result<(), ApiError> {
+        if !self.token_cache {
+            return Ok(());
+        }
+        let Some(dir) = self.cache_dir.clone().or_else(env_cache_dir) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            ApiError::with_kind(
+                Kind::Io,
+                format!("could not create cache directory {}: {e}", dir.display()),
+            )
+        })
+    }
+}
+
+/// Client for the SideFX Web API.
+///
+/// Cloning is cheap: clones share the HTTP connection pool and the concurrent request limit,
+/// so one authorized client can be handed to many tasks.
+///
+/// All methods are cancellation safe: dropping a future, e.g. the losing branch of a
+/// `tokio::select!`, aborts the request and leaves no partially written files behind.
+///
+/// When the API rejects the access token with 401 or 403, e.g. a cached token revoked before
+/// its expiry, the token is dropped from the cache, a new one requested and the call sent
+/// again, once. Only if that fails too is it an error.
+#[derive(Clone)]
 pub struct SesiClient {
-    token: String,
+    /// Replaced when the API rejects it, see [`SesiClient::reauthorize`].
+    token: Arc<RwLock<String>>,
+    credentials: Arc<Credentials>,
     client: ReqwestClient,
+    limiter: Arc<Semaphore>,
+    api_retries: u32,
+    api_url: String,
+    /// Where listings are cached, `None` if disabled.
+    cache_dir: Option<PathBuf>,
+    trace_http: bool,
+    headers: HeaderMap,
+}
+
+/// The calls of [`SesiClient`] most tools built on this crate need, for code that should also
+/// run against a stand-in, like `testing::MockClient` with the `testing` feature, in tests
+/// without network access or credentials.
+pub trait BuildApi {
+    /// See [`SesiClient::list_builds_with`].
+    fn list_builds(
+        &self,
+        parms: ListBuildsParms,
+    ) -> impl Future<Output = Result<Vec<Build>, ApiError>> + Send;
+
+    /// See [`SesiClient::get_build_url`].
+    fn get_build_url(
+        &self,
+        product: Product,
+        platform: Platform,
+        version: String,
+        build: u64,
+    ) -> impl Future<Output = Result<BuildUrl, ApiError>> + Send;
+}
+
+impl BuildApi for SesiClient {
+    fn list_builds(
+        &self,
+        parms: ListBuildsParms,
+    ) -> impl Future<Output = Result<Vec<Build>, ApiError>> + Send {
+        self.list_builds_with(parms)
+    }
+
+    fn get_build_url(
+        &self,
+        product: Product,
+        platform: Platform,
+        version: String,
+        build: u64,
+    ) -> impl Future<Output = Result<BuildUrl, ApiError>> + Send {
+        SesiClient::get_build_url(self, product, platform, version, build)
+    }
+}
+
+/// What [`get_access_token`] needs to request a new token.
+struct Credentials {
+    user_id: String,
+    user_secret: String,
+    config: ClientConfig,
+}
+
+/// Add [`ClientConfig::headers`] to a request, except `Authorization`, which the request sets
+/// from the credentials.
+fn with_headers(
+    mut request: reqwest::RequestBuilder,
+    headers: &HeaderMap,
+) -> reqwest::RequestBuilder {
+    for (name, value) in headers {
+        if name != reqwest::header::AUTHORIZATION {
+            request = request.header(name, value);
+        }
+    }
+    request
 }
 
 impl SesiClient {
     pub async fn new(user_id: &str, user_secret: &str) -> Result<Self, ApiError> {
-        let client = ReqwestClient::new();
-        let token = get_access_token(&client, user_id, user_secret).await?;
-        Ok(SesiClient { token, client })
+        Self::with_config(user_id, user_secret, ClientConfig::default()).await
+    }
+
+    /// Authorize with the options of `config`.
+    ///
+    /// A token endpoint that answers with something other than JSON, like the HTML page SideFX
+    /// shows during maintenance, is retried [`ClientConfig::api_retries`] times and then
+    /// reported as a [`Kind::Request`] error.
+    pub async fn with_config(
+        user_id: &str,
+        user_secret: &str,
+        config: ClientConfig,
+    ) -> Result<Self, ApiError> {
+        let client = config.http_client()?;
+        Self::authorize(client, user_id, user_secret, config).await
+    }
+
+    /// Authorize using an existing HTTP client, with the default [`ClientConfig`] otherwise.
+    ///
+    /// Every [`SesiClient::new`] builds its own connection pool, so sharing one client between
+    /// several `SesiClient`s and the downloads is the recommended pattern for high-volume usage.
+    /// The client's own settings, like proxies and timeouts, apply to all requests:
+    ///
+    /// ```no_run
+    /// use houdini_downloader_api::SesiClient;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), houdini_downloader_api::ApiError> {
+    /// let client = reqwest::Client::builder()
+    ///     .proxy(reqwest::Proxy::all("http://proxy.example.com:3128")?)
+    ///     .build()?;
+    /// let sesi = SesiClient::with_client(client.clone(), "id", "secret").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn with_client(
+        client: ReqwestClient,
+        user_id: &str,
+        user_secret: &str,
+    ) -> Result<Self, ApiError> {
+        Self::with_client_and_config(client, user_id, user_secret, ClientConfig::default()).await
+    }
+
+    /// Authorize using an existing HTTP client and the other options of `config`. The options
+    /// that go into building a client, like [`ClientConfig::user_agent`] or
+    /// [`ClientConfig::root_certificates`], are taken from `client` instead.
+    pub async fn with_client_and_config(
+        client: ReqwestClient,
+        user_id: &str,
+        user_secret: &str,
+        config: ClientConfig,
+    ) -> Result<Self, ApiError> {
+        Self::authorize(client, user_id, user_secret, config).await
+    }
+
+    async fn authorize(
+        client: ReqwestClient,
+        user_id: &str,
+        user_secret: &str,
+        config: ClientConfig,
+    ) -> Result<Self, ApiError> {
+        config.create_cache_dir()?;
+        let token = get_access_token(&client, user_id, user_secret, &config).await?;
+        Ok(SesiClient {
+            token: Arc::new(RwLock::new(token)),
+            client,
+            limiter: Arc::new(Semaphore::new(config.max_concurrent_requests.max(1))),
+            api_retries: config.api_retries,
+            api_url: config.api_url.clone(),
+            trace_http: config.trace_http,
+            headers: config.headers.clone(),
+            cache_dir: config.listing_cache_dir(),
+            credentials: Arc::new(Credentials {
+                user_id: user_id.to_string(),
+                user_secret: user_secret.to_string(),
+                config,
+            }),
+        })
+    }
+
+    /// The underlying HTTP client, configured the same way as API requests.
+    /// Use it to download the build files.
+    pub fn http_client(&self) -> &ReqwestClient {
+        &self.client
     }
 
     pub async fn list_builds(
@@ -182,17 +1204,87 @@ impl SesiClient {
         version: Option<impl Into<String>>,
         only_production: bool,
     ) -> Result<Vec<Build>, ApiError> {
-        let body = self
-            .call_api(EndPoint::ListBuilds(ListBuildsParms {
-                product,
-                platform,
-                version: version.map(|t| t.into()),
-                only_production,
+        self.list_builds_with(ListBuildsParms {
+            product,
+            platform,
+            version: version.map(|t| t.into()),
+            only_production,
+        })
+        .await
+    }
+
+    /// Same as [`SesiClient::list_builds`], but takes parameters made with [`ListBuildsParms::builder`].
+    pub async fn list_builds_with(&self, parms: ListBuildsParms) -> Result<Vec<Build>, ApiError> {
+        Ok(Vec::<Build>::deserialize(
+            &self.list_builds_raw(parms).await?,
+        )?)
+    }
+
+    /// The listing as untyped JSON, to read fields [`Build`] doesn't have yet
+    /// or to inspect a response that fails to decode.
+    pub async fn list_builds_raw(&self, parms: ListBuildsParms) -> Result<Value, ApiError> {
+        let body = self.call_api(EndPoint::ListBuilds(parms)).await?;
+        decode_raw(&body)
+    }
+
+    /// List builds for every [`Platform`] concurrently and merge the results.
+    /// Products that don't [require a platform](Product::requires_platform) are listed once.
+    pub async fn list_builds_all_platforms(
+        &self,
+        product: Product,
+        version: Option<impl Into<String>>,
+        only_production: bool,
+    ) -> Result<Vec<Build>, ApiError> {
+        let version: Option<String> = version.map(Into::into);
+        if !product.requires_platform() {
+            return self
+                .list_builds(product, default_platform(), version, only_production)
+                .await;
+        }
+        let results =
+            futures_util::future::join_all(Platform::ALL.map(|platform| {
+                self.list_builds(product, platform, version.clone(), only_production)
             }))
+            .await;
+        let mut builds = Vec::new();
+        for result in results {
+            builds.extend(result?);
+        }
+        Ok(builds)
+    }
+
+    /// Distinct versions with at least one build, including daily builds, oldest first.
+    pub async fn list_versions(
+        &self,
+        product: Product,
+        platform: Platform,
+    ) -> Result<Vec<String>, ApiError> {
+        let mut builds = self
+            .list_builds(product, platform, None::<String>, false)
             .await?;
-        serde_json::from_slice(&body).map_err(|e| ApiError::new(e))
+        builds.sort_by_key(Build::version_tuple);
+        let mut versions: Vec<String> = builds.into_iter().map(|b| b.version).collect();
+        versions.dedup();
+        Ok(versions)
     }
 
+    /// Look up a single build in the listing, including daily builds.
+    /// `Ok(None)` if the version has no such build.
+    pub async fn find_build(
+        &self,
+        product: Product,
+        platform: Platform,
+        version: impl Into<String>,
+        build: u64,
+    ) -> Result<Option<Build>, ApiError> {
+        let builds = self
+            .list_builds(product, platform, Some(version), false)
+            .await?;
+        Ok(builds.into_iter().find(|b| b.build == build))
+    }
+
+    /// Resolve the download URL of a build. A build that doesn't exist is reported as a
+    /// [`Kind::NotFound`] error, whether SideFX answers with no result or an error message.
     pub async fn get_build_url(
         &self,
         product: Product,
@@ -206,57 +1298,731 @@ impl SesiClient {
             version: version.into(),
             build,
         };
-        let body = self.call_api(EndPoint::Download(parms)).await?;
+        self.fetch_build_url(parms).await
+    }
 
-        serde_json::from_slice(&body).map_err(|_| ApiError::new(String::from_utf8_lossy(&body)))
+    /// Resolve the download URL of a build returned by [`SesiClient::list_builds`].
+    pub async fn get_build_url_for(&self, build: &Build) -> Result<BuildUrl, ApiError> {
+        self.fetch_build_url(DownloadParms::for_build(build)).await
     }
 
-    async fn call_api(&self, endpoint: EndPoint) -> reqwest::Result<Bytes> {
-        let (method, parms) = match endpoint {
-            EndPoint::ListBuilds(parms) => (
-                "download.get_daily_builds_list",
-                serde_json::to_value(parms).unwrap(),
-            ),
-            EndPoint::Download(parms) => (
-                "download.get_daily_build_download",
-                serde_json::to_value(parms).unwrap(),
-            ),
+    /// Resolve the download URLs of several builds of a version concurrently, at most
+    /// [`ClientConfig::max_concurrent_requests`] at a time. The results are in the order of
+    /// `builds`, each one failing on its own.
+    pub async fn get_build_urls(
+        &self,
+        product: Product,
+        platform: Platform,
+        version: impl Into<String>,
+        builds: impl IntoIterator<Item = u64>,
+    ) -> Vec<Result<BuildUrl, ApiError>> {
+        let version: String = version.into();
+        // Each request waits for a permit of the limiter, which bounds the concurrency
+        futures_util::future::join_all(
+            builds
+                .into_iter()
+                .map(|build| self.get_build_url(product, platform.clone(), version.clone(), build)),
+        )
+        .await
+    }
+
+    /// Download a build into `dir`, see [`download::download_build_to`].
+    pub async fn download_build_to(
+        &self,
+        url: &BuildUrl,
+        dir: &Path,
+        opts: &download::TransferOptions,
+    ) -> Result<download::DownloadOutcome, ApiError> {
+        download::download_build_to(&self.client, url, dir, opts).await
+    }
+
+    /// Download a small file into memory, up to [`ClientConfig::max_in_memory`] bytes, see
+    /// [`download::download_to_bytes`].
+    pub async fn download_to_bytes(&self, url: &BuildUrl) -> Result<(Bytes, String), ApiError> {
+        download::download_to_bytes(&self.client, url, self.credentials.config.max_in_memory).await
+    }
+
+    /// Same as [`SesiClient::get_build_url`], but returns the untyped JSON.
+    pub async fn get_build_url_raw(
+        &self,
+        product: Product,
+        platform: Platform,
+        version: impl Into<String>,
+        build: u64,
+    ) -> Result<Value, ApiError> {
+        let parms = DownloadParms {
+            product,
+            platform,
+            version: version.into(),
+            build,
         };
-        let parms = json!([method, [], parms]).to_string();
-        self.client
-            .post(ENDPOINT_URL)
-            .bearer_auth(&self.token)
-            .form(&[("json", parms)])
-            .send()
+        self.fetch_build_url_raw(parms).await
+    }
+
+    async fn fetch_build_url_raw(&self, parms: DownloadParms) -> Result<Value, ApiError> {
+        let body = self.call_api(EndPoint::Download(parms)).await?;
+        decode_raw(&body)
+    }
+
+    async fn fetch_build_url(&self, parms: DownloadParms) -> Result<BuildUrl, ApiError> {
+        let build = format!("{}.{}", parms.version, parms.build);
+        let platform = parms.platform.clone();
+        let raw = self.fetch_build_url_raw(parms).await?;
+        if is_not_found(&raw) {
+            return Err(ApiError::with_kind(
+                Kind::NotFound,
+                format!("build {build} not found for {platform}"),
+            ));
+        }
+        BuildUrl::deserialize(&raw).map_err(|_| ApiError::with_kind(Kind::Decode, raw.to_string()))
+    }
+
+    /// Like [`SesiClient::list_builds_with`], but decodes builds one by one while the
+    /// response is still downloading, instead of buffering the whole listing.
+    pub async fn list_builds_stream(
+        &self,
+        parms: ListBuildsParms,
+    ) -> Result<impl Stream<Item = Result<Build, ApiError>>, ApiError> {
+        let permit = self
+            .limiter
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let body = self
+            .send_api(&EndPoint::ListBuilds(parms).form(), None)
             .await?
-            .bytes()
+            .bytes_stream();
+        let state = (body, stream::ArraySplitter::default(), permit);
+        Ok(futures_util::stream::unfold(
+            Some(state),
+            |state| async move {
+                let (mut body, mut splitter, permit) = state?;
+                loop {
+                    match splitter.next_element() {
+                        Ok(Some(element)) => {
+                            let build = serde_json::from_slice(&element).map_err(ApiError::from);
+                            return Some((build, Some((body, splitter, permit))));
+                        }
+                        Ok(None) if splitter.is_done() => return None,
+                        Ok(None) => {}
+                        Err(e) => return Some((Err(e), None)),
+                    }
+                    match body.next().await {
+                        Some(Ok(chunk)) => splitter.feed(&chunk),
+                        Some(Err(e)) => return Some((Err(e.into()), None)),
+                        None => {
+                            return Some((
+                                Err(ApiError::with_kind(
+                                    Kind::Decode,
+                                    "build listing ended unexpectedly",
+                                )),
+                                None,
+                            ))
+                        }
+                    }
+                }
+            },
+        ))
+    }
+
+    async fn call_api(&self, endpoint: EndPoint) -> Result<Bytes, ApiError> {
+        let cache_file = match (&endpoint, &self.cache_dir) {
+            (EndPoint::ListBuilds(_), Some(dir)) => Some(dir),
+            _ => None,
+        };
+        let form = endpoint.form();
+        let cache_file = cache_file.map(|dir| CachedListing::path(dir, &form));
+        let cached = cache_file.as_deref().and_then(CachedListing::load);
+        let _permit = self
+            .limiter
+            .acquire()
             .await
+            .expect("semaphore is never closed");
+        let resp = self.send_api(&form, cached.as_ref()).await?;
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (resp.status(), cached) {
+            trace(self.trace_http, || {
+                "listing not modified, using the cached one".into()
+            });
+            return Ok(cached.body.into());
+        }
+        let success = resp.status().is_success();
+        let headers = resp.headers().clone();
+        let body = resp.bytes().await?;
+        trace(self.trace_http, || {
+            format!("response: {}", String::from_utf8_lossy(&body))
+        });
+        if let Some(cache_file) = cache_file.filter(|_| success) {
+            if let Some(listing) = CachedListing::from_response(&headers, &body) {
+                listing.save(&cache_file);
+            }
+        }
+        Ok(body)
+    }
+
+    /// Send the request, retrying when rate limited. The caller holds a limiter permit.
+    /// With a `cached` response the request is conditional and may return 304 Not Modified.
+    /// A rejected token is replaced once, and the request sent again with the new one.
+    async fn send_api(
+        &self,
+        form: &str,
+        cached: Option<&CachedListing>,
+    ) -> Result<reqwest::Response, ApiError> {
+        let mut attempt = 0;
+        let mut reauthorized = false;
+        loop {
+            let token = self.token.read().await.clone();
+            let mut request = with_headers(self.client.post(&self.api_url), &self.headers)
+                .bearer_auth(&token)
+                .form(&[("json", form)]);
+            if let Some(cached) = cached {
+                request = cached.validate(request);
+            }
+            // The bearer token is a header and not part of the traced form
+            trace(self.trace_http, || {
+                format!("POST {} json={form}", self.api_url)
+            });
+            let resp = request.send().await?;
+            trace(self.trace_http, || format!("status: {}", resp.status()));
+            if matches!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+            ) {
+                if reauthorized {
+                    return Err(ApiError::with_kind(
+                        Kind::Auth,
+                        format!("SideFX rejected a new access token: {}", resp.status()),
+                    ));
+                }
+                reauthorized = true;
+                self.reauthorize(&token).await?;
+                continue;
+            }
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS
+                && attempt < self.api_retries
+                && wait_rate_limited(&self.credentials.config, &resp, attempt + 1).await
+            {
+                attempt += 1;
+                continue;
+            }
+            return Ok(resp);
+        }
+    }
+
+    /// Replace the `rejected` token, e.g. one revoked before its recorded expiry, with a new
+    /// one, dropping it from the token cache. Concurrent requests rejected with the same token
+    /// wait for the first to get a new one and then use it.
+    async fn reauthorize(&self, rejected: &str) -> Result<(), ApiError> {
+        let mut token = self.token.write().await;
+        if *token != rejected {
+            return Ok(());
+        }
+        trace(self.trace_http, || {
+            "access token rejected, authorizing again".into()
+        });
+        let credentials = &self.credentials;
+        if let Some(token_file) = credentials.config.token_cache_file() {
+            let _ = std::fs::remove_file(token_file);
+        }
+        *token = get_access_token(
+            &self.client,
+            &credentials.user_id,
+            &credentials.user_secret,
+            &credentials.config,
+        )
+        .await?;
+        Ok(())
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// A build from the listing. Only `build`, `version` and `platform` are required,
+/// the informational fields are left empty if SideFX stops sending them:
+///
+/// ```
+/// use houdini_downloader_api::Build;
+///
+/// let build: Build = serde_json::from_str(r#"{"build": "805", "date": "2023/11/21",
+///     "product": "houdini", "platform": "linux_x86_64_gcc9.3", "status": "good",
+///     "version": "19.5"}"#).unwrap();
+/// assert_eq!(build.build, 805);
+/// assert_eq!(build.release, "");
+/// ```
+///
+/// Serialized, the build number is a number, which reads back too:
+///
+/// ```
+/// # use houdini_downloader_api::Build;
+/// # let build: Build = serde_json::from_str(r#"{"build": "805", "product": "houdini",
+/// #     "platform": "linux_x86_64_gcc9.3", "version": "19.5"}"#).unwrap();
+/// let json = serde_json::to_value(&build).unwrap();
+/// assert_eq!(json["build"], 805);
+/// let read: Build = serde_json::from_value(json).unwrap();
+/// assert_eq!(read.build, 805);
+/// assert_eq!(read.version, "19.5");
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Build {
     #[serde(deserialize_with = "parse_build_number")]
     pub build: u64,
+    #[serde(default)]
     pub date: String, // TODO: Use chrono
     pub product: Product,
     pub platform: String,
+    #[serde(default)]
     pub release: String,
+    #[serde(default)]
     pub status: String,
     pub version: String,
 }
 
+/// Release channel of a build, normalized from [`Build::release`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReleaseChannel {
+    Gold,
+    Beta,
+    Other(String),
+}
+
+/// What to sort builds by with [`BuildOrder::sort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildOrder {
+    /// Build number, then version.
+    Build,
+    /// [`Build::date_days`], then version and build. Builds without a date come last.
+    Date,
+    /// [`Build::version_tuple`], so "19.10" comes before "19.5" and build 1000 before build 999.
+    Version,
+}
+
+impl BuildOrder {
+    /// Sort `builds` newest first, comparing numbers rather than strings. Builds that compare
+    /// equal keep their order.
+    ///
+    /// ```
+    /// use houdini_downloader_api::{Build, BuildOrder};
+    ///
+    /// let build = |version: &str, build: u64, date: &str| Build {
+    ///     version: version.to_string(),
+    ///     build,
+    ///     date: date.to_string(),
+    ///     ..Build::default()
+    /// };
+    /// let mut builds = vec![
+    ///     build("19.5", 1000, "2024/01/10"),
+    ///     build("19.10", 999, "2024/01/09"),
+    ///     build("19.5", 999, ""),
+    ///     build("20.0", 120, "2024/01/11"),
+    /// ];
+    /// let order = |builds: &[Build]| -> Vec<String> {
+    ///     builds.iter().map(|b| format!("{}.{}", b.version, b.build)).collect()
+    /// };
+    ///
+    /// BuildOrder::Version.sort(&mut builds);
+    /// assert_eq!(order(&builds), ["20.0.120", "19.10.999", "19.5.1000", "19.5.999"]);
+    /// BuildOrder::Build.sort(&mut builds);
+    /// assert_eq!(order(&builds), ["19.5.1000", "19.10.999", "19.5.999", "20.0.120"]);
+    /// BuildOrder::Date.sort(&mut builds);
+    /// assert_eq!(order(&builds), ["20.0.120", "19.5.1000", "19.10.999", "19.5.999"]);
+    /// ```
+    pub fn sort(self, builds: &mut [Build]) {
+        match self {
+            BuildOrder::Build => builds.sort_by_key(|b| Reverse((b.build, b.version_tuple()))),
+            BuildOrder::Date => builds.sort_by_key(|b| Reverse((b.date_days(), b.version_tuple()))),
+            BuildOrder::Version => builds.sort_by_key(|b| Reverse(b.version_tuple())),
+        }
+    }
+}
+
+impl Build {
+    /// The normalized release channel.
+    ///
+    /// ```
+    /// use houdini_downloader_api::{Build, ReleaseChannel};
+    ///
+    /// let builds: Vec<Build> = serde_json::from_str(r#"[
+    ///     {"build": "805", "product": "houdini", "platform": "linux", "release": "gold", "version": "19.5"},
+    ///     {"build": "807", "product": "houdini", "platform": "linux", "release": " Beta", "version": "19.5"},
+    ///     {"build": "808", "product": "houdini", "platform": "linux", "release": "devel", "version": "19.5"}
+    /// ]"#).unwrap();
+    /// let beta: Vec<u64> = builds
+    ///     .iter()
+    ///     .filter(|b| b.release_channel() == ReleaseChannel::Beta)
+    ///     .map(|b| b.build)
+    ///     .collect();
+    /// assert_eq!(beta, [807]);
+    /// assert_eq!(builds[2].release_channel(), ReleaseChannel::Other("devel".into()));
+    /// ```
+    pub fn release_channel(&self) -> ReleaseChannel {
+        let release = self.release.trim().to_lowercase();
+        match release.as_str() {
+            "gold" => ReleaseChannel::Gold,
+            "beta" => ReleaseChannel::Beta,
+            _ => ReleaseChannel::Other(release),
+        }
+    }
+
+    /// Whether SideFX flagged the build as defective.
+    pub fn is_bad(&self) -> bool {
+        self.status == "bad"
+    }
+
+    /// Whether SideFX marked the build as good, unlike a bad one or one not rated yet.
+    pub fn is_good(&self) -> bool {
+        self.status == "good"
+    }
+
+    /// Whether the build number is greater than `since` and at most `until`.
+    ///
+    /// ```
+    /// # use houdini_downloader_api::Build;
+    /// let build = |build: u64| -> Build {
+    ///     serde_json::from_value(serde_json::json!({"build": build.to_string(),
+    ///         "product": "houdini", "platform": "linux", "version": "20.0"})).unwrap()
+    /// };
+    /// assert!(!build(500).is_in_build_range(Some(500), None));
+    /// assert!(build(501).is_in_build_range(Some(500), None));
+    /// assert!(build(510).is_in_build_range(Some(500), Some(510)));
+    /// assert!(!build(511).is_in_build_range(None, Some(510)));
+    /// ```
+    pub fn is_in_build_range(&self, since: Option<u64>, until: Option<u64>) -> bool {
+        since.is_none_or(|since| self.build > since)
+            && until.is_none_or(|until| self.build <= until)
+    }
+
+    /// The date as days since 1970-01-01, for comparing dates. SideFX writes them `YYYY/MM/DD`,
+    /// `YYYY-MM-DD` is accepted too. `None` if the date is missing or doesn't parse.
+    ///
+    /// ```
+    /// use houdini_downloader_api::Build;
+    ///
+    /// let build = |date: &str| Build { date: date.to_string(), ..Build::default() };
+    /// assert_eq!(build("1970/01/01").date_days(), Some(0));
+    /// assert_eq!(build("2023/11/21").date_days(), Some(19682));
+    /// assert_eq!(build("2024-03-01").date_days(), Some(19783));
+    /// assert_eq!(build("2023/13/01").date_days(), None);
+    /// assert_eq!(build("").date_days(), None);
+    /// ```
+    pub fn date_days(&self) -> Option<i64> {
+        let mut parts = self
+            .date
+            .trim()
+            .split(['/', '-'])
+            .map(|part| part.parse::<i64>().ok());
+        let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+        if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        // Howard Hinnant's days_from_civil, with years starting in March so leap days come last
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Some(era * 146097 + day_of_era - 719468)
+    }
+
+    /// Numeric `(major, minor, build)` for ordering builds, where "19.10" is newer than "19.5".
+    /// `None` if the version string isn't `major.minor`.
+    ///
+    /// ```
+    /// # let build: houdini_downloader_api::Build = serde_json::from_str(r#"{"build": "805",
+    /// #   "date": "2023/11/21", "product": "houdini", "platform": "linux_x86_64_gcc9.3",
+    /// #   "release": "gold", "status": "good", "version": "19.5"}"#).unwrap();
+    /// assert_eq!(build.version_tuple(), Some((19, 5, 805)));
+    /// ```
+    pub fn version_tuple(&self) -> Option<(u32, u32, u64)> {
+        let version: Version = self.version.parse().ok()?;
+        Some((version.major, version.minor, self.build))
+    }
+
+    /// Python version named in the platform string, e.g. "3.9" for "linux_x86_64_gcc9.3_py39".
+    /// The listing endpoint has no Python parameter, so when SideFX ships builds for several
+    /// Python versions this tag is the only way to tell them apart. `None` if there is none.
+    ///
+    /// ```
+    /// use houdini_downloader_api::Build;
+    ///
+    /// let build = |platform: &str| Build { platform: platform.to_string(), ..Build::default() };
+    /// assert_eq!(build("linux_x86_64_gcc9.3_py39").python_version().as_deref(), Some("3.9"));
+    /// assert_eq!(build("win64-vc143-py310").python_version().as_deref(), Some("3.10"));
+    /// assert_eq!(build("linux_x86_64_gcc9.3").python_version(), None);
+    /// ```
+    pub fn python_version(&self) -> Option<String> {
+        python_version_tag(&self.platform)
+    }
+
+    /// Version and build number, e.g. "19.5.805".
+    pub fn full_version(&self) -> String {
+        format!("{}.{}", self.version, self.build)
+    }
+
+    /// The canonical one-line description, also used by `Display`.
+    ///
+    /// ```
+    /// use houdini_downloader_api::Build;
+    ///
+    /// let build = Build {
+    ///     build: 805,
+    ///     date: "2023/11/21".to_string(),
+    ///     platform: "linux_x86_64_gcc9.3".to_string(),
+    ///     release: "gold".to_string(),
+    ///     status: "good".to_string(),
+    ///     version: "19.5".to_string(),
+    ///     ..Build::default()
+    /// };
+    /// assert_eq!(
+    ///     build.summary_line(),
+    ///     "houdini 19.5.805 linux_x86_64_gcc9.3, 2023/11/21, status: good, release: gold"
+    /// );
+    /// assert_eq!(build.to_string(), build.summary_line());
+    /// ```
+    pub fn summary_line(&self) -> String {
+        format!(
+            "{} {} {}, {}, status: {}, release: {}",
+            self.product,
+            self.full_version(),
+            self.platform,
+            self.date,
+            self.status,
+            self.release
+        )
+    }
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Build {
+            build: 0,
+            date: String::new(),
+            product: Product::Houdini,
+            platform: String::new(),
+            release: String::new(),
+            status: String::new(),
+            version: String::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for Build {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.summary_line())
+    }
+}
+
+/// A product version as users type it: `major.minor`, optionally followed by the build number.
+///
+/// ```
+/// use houdini_downloader_api::Version;
+///
+/// let version: Version = "20.0".parse().unwrap();
+/// assert_eq!((version.major, version.minor, version.build), (20, 0, None));
+/// assert_eq!(version.to_string(), "20.0");
+///
+/// let version: Version = "20.0.547".parse().unwrap();
+/// assert_eq!(version.build, Some(547));
+/// assert_eq!(version.without_build().to_string(), "20.0");
+///
+/// for invalid in ["20", "20.", "20.x", "20.0.547.1", ""] {
+///     assert!(invalid.parse::<Version>().is_err());
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub build: Option<u64>,
+}
+
+impl Version {
+    /// The `major.minor` part, as the API expects it.
+    pub fn without_build(&self) -> Version {
+        Version {
+            build: None,
+            ..*self
+        }
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseVersionError(s.to_string());
+        let mut parts = s.trim().split('.');
+        let major = parts.next().and_then(|p| p.parse().ok()).ok_or_else(err)?;
+        let minor = parts.next().and_then(|p| p.parse().ok()).ok_or_else(err)?;
+        let build = match parts.next() {
+            Some(build) => Some(build.parse().map_err(|_| err())?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return Err(err());
+        }
+        Ok(Version {
+            major,
+            minor,
+            build,
+        })
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(build) = self.build {
+            write!(f, ".{build}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A string that isn't a [`Version`].
+#[derive(Debug)]
+pub struct ParseVersionError(String);
+
+impl std::fmt::Display for ParseVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid version {:?}, expected major.minor [e.g. 19.5] or major.minor.build",
+            self.0
+        )
+    }
+}
+
+impl StdError for ParseVersionError {}
+
+/// The API sends the build number as a string, [`Build`] serializes it as a number.
 fn parse_build_number<'de, D: serde::Deserializer<'de>>(des: D) -> Result<u64, D::Error> {
-    let str_val = String::deserialize(des)?;
-    str_val
-        .parse()
-        .map_err(|_| Error::custom("build is not a number"))
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BuildNumber {
+        Number(u64),
+        Text(String),
+    }
+    match BuildNumber::deserialize(des)? {
+        BuildNumber::Number(build) => Ok(build),
+        BuildNumber::Text(text) => text
+            .parse()
+            .map_err(|_| Error::custom("build is not a number")),
+    }
 }
 
-#[derive(Debug, Deserialize)]
+fn parse_size<'de, D: serde::Deserializer<'de>>(des: D) -> Result<u64, D::Error> {
+    Ok(Option::<u64>::deserialize(des)?.unwrap_or(0))
+}
+
+/// Checksum algorithm of a [`BuildUrl::hash`].
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HashAlgo {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgo {
+    /// Guess the algorithm from the length of a hex digest.
+    ///
+    /// ```
+    /// use houdini_downloader_api::HashAlgo;
+    ///
+    /// assert_eq!(HashAlgo::from_hex_len("f355bfe7271e0755908a3680f1f3c619"), Some(HashAlgo::Md5));
+    /// assert_eq!(HashAlgo::from_hex_len(&"a".repeat(40)), Some(HashAlgo::Sha1));
+    /// assert_eq!(HashAlgo::from_hex_len(&"a".repeat(64)), Some(HashAlgo::Sha256));
+    /// assert_eq!(HashAlgo::from_hex_len("not a hash"), None);
+    /// ```
+    pub fn from_hex_len(hash: &str) -> Option<HashAlgo> {
+        if !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        match hash.len() {
+            32 => Some(HashAlgo::Md5),
+            40 => Some(HashAlgo::Sha1),
+            64 => Some(HashAlgo::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Whether downloads hashed with this algorithm can be verified.
+    pub fn is_supported(&self) -> bool {
+        checksum::Hasher::new(*self).is_some()
+    }
+}
+
+impl std::fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HashAlgo::Md5 => "md5",
+            HashAlgo::Sha1 => "sha1",
+            HashAlgo::Sha256 => "sha256",
+        })
+    }
+}
+
+/// Where to download a build.
+///
+/// A size SideFX leaves out or sends as `null` is 0, meaning unknown, see
+/// [`download::remote_size`]:
+///
+/// ```
+/// use houdini_downloader_api::BuildUrl;
+///
+/// let url: BuildUrl = serde_json::from_str(
+///     r#"{"download_url": "https://example.com/houdini.tar.gz",
+///         "filename": "houdini.tar.gz", "hash": "f355bfe7271e0755908a3680f1f3c619"}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(url.size, 0);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildUrl {
     pub download_url: String,
     pub filename: String,
     pub hash: String,
+    /// Size in bytes, 0 if unknown.
+    #[serde(default, deserialize_with = "parse_size")]
     pub size: u64,
 }
+
+impl BuildUrl {
+    /// Python version named in the installer file name, e.g. "3.9" for
+    /// "houdini-py39-19.5.805-linux_x86_64_gcc9.3.tar.gz", `None` if there is none.
+    pub fn python_version(&self) -> Option<String> {
+        python_version_tag(&self.filename)
+    }
+}
+
+/// Size in binary units with two decimals, the way sizes are shown everywhere in `houdl`.
+///
+/// ```
+/// use houdini_downloader_api::format_bytes;
+///
+/// assert_eq!(format_bytes(512), "512 B");
+/// assert_eq!(format_bytes(1536), "1.50 KiB");
+/// assert_eq!(format_bytes(2_007_880_704), "1.87 GiB");
+/// ```
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.2} {}", UNITS[unit])
+}
+
+/// Find a `py<major><minor>` tag between `-`, `_` or `.` separators and format it as "major.minor".
+fn python_version_tag(name: &str) -> Option<String> {
+    name.split(['-', '_', '.'])
+        .filter_map(|part| part.strip_prefix("py"))
+        .find(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+        .map(|digits| match digits.split_at(1) {
+            (major, "") => major.to_string(),
+            (major, minor) => format!("{major}.{minor}"),
+        })
+}
@@ -0,0 +1,68 @@
+use md5::{Digest, Md5};
+use reqwest::header::{self, HeaderMap};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A listing response saved with the validators SideFX sent, so the next identical
+/// request can be made conditional. Responses without validators are not cached.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct CachedListing {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    pub(crate) body: String,
+}
+
+impl CachedListing {
+    /// Cache file for the request form, inside `dir`.
+    pub(crate) fn path(dir: &Path, form: &str) -> PathBuf {
+        let key = hex::encode(Md5::digest(form.as_bytes()));
+        dir.join("listings").join(key).with_extension("json")
+    }
+
+    pub(crate) fn load(path: &Path) -> Option<CachedListing> {
+        let data = std::fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// `None` if the response has neither an `ETag` nor a `Last-Modified` header.
+    pub(crate) fn from_response(headers: &HeaderMap, body: &[u8]) -> Option<CachedListing> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(header::ETAG);
+        let last_modified = header(header::LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(CachedListing {
+            etag,
+            last_modified,
+            body: String::from_utf8(body.to_vec()).ok()?,
+        })
+    }
+
+    pub(crate) fn save(&self, path: &Path) {
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        let _ = std::fs::create_dir_all(dir);
+        if let Ok(data) = serde_json::to_vec(self) {
+            let _ = crate::write_atomic(path, &data);
+        }
+    }
+
+    /// Make the request conditional on the cached response still being current.
+    pub(crate) fn validate(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut request = request;
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
@@ -0,0 +1,90 @@
+//! Credentials from a `.netrc` file, as curl and many other tools read them.
+//!
+//! The entry for [`MACHINE`] (or the `default` entry) is used: its `login` is the
+//! user id and its `password` the user secret of the SideFX Web API.
+//!
+//! ```
+//! use houdini_downloader_api::netrc;
+//!
+//! let contents = "
+//! machine example.com login someone password hunter2
+//!
+//! machine sidefx.com
+//!     login my-client-id
+//!     password my-client-secret
+//! ";
+//! assert_eq!(
+//!     netrc::find(contents, netrc::MACHINE),
+//!     Some(("my-client-id".to_string(), "my-client-secret".to_string()))
+//! );
+//! assert_eq!(netrc::find("default login a password b", netrc::MACHINE),
+//!     Some(("a".to_string(), "b".to_string())));
+//! assert_eq!(netrc::find("machine example.com login a password b", netrc::MACHINE), None);
+//! ```
+
+use std::path::PathBuf;
+
+/// Machine name of the SideFX entry.
+pub const MACHINE: &str = "sidefx.com";
+
+/// `$NETRC` if set, otherwise `.netrc` (`_netrc` on Windows) in the home directory.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    dirs::home_dir().map(|home| home.join(name))
+}
+
+/// Login and password of the `machine` entry, or of the `default` entry if there is none.
+/// `None` unless the entry has both.
+pub fn find(contents: &str, machine: &str) -> Option<(String, String)> {
+    let mut tokens = contents.split_whitespace();
+    let mut entries = Vec::new();
+    // (machine or None for default, login, password)
+    let mut current: Option<(Option<&str>, Option<&str>, Option<&str>)> = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" | "default" => {
+                entries.extend(current.take());
+                let name = if token == "machine" {
+                    Some(tokens.next()?)
+                } else {
+                    None
+                };
+                current = Some((name, None, None));
+            }
+            "login" => {
+                let login = tokens.next();
+                if let Some(entry) = &mut current {
+                    entry.1 = login;
+                }
+            }
+            "password" => {
+                let password = tokens.next();
+                if let Some(entry) = &mut current {
+                    entry.2 = password;
+                }
+            }
+            "account" => {
+                tokens.next();
+            }
+            // Macro definitions run until an empty line, which whitespace splitting can't see,
+            // so nothing after one is trusted
+            "macdef" => break,
+            _ => {}
+        }
+    }
+    entries.extend(current);
+    let entry = entries
+        .iter()
+        .find(|entry| entry.0 == Some(machine))
+        .or_else(|| entries.iter().find(|entry| entry.0.is_none()))?;
+    Some((entry.1?.to_string(), entry.2?.to_string()))
+}
+
+/// Credentials for [`MACHINE`] from the file at [`path`], `None` if it can't be read or has none.
+pub fn credentials() -> Option<(String, String)> {
+    let contents = std::fs::read_to_string(path()?).ok()?;
+    find(&contents, MACHINE)
+}
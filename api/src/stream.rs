@@ -0,0 +1,75 @@
+use crate::{ApiError, Kind};
+
+/// Incrementally splits a JSON array into its elements as bytes arrive.
+#[derive(Default)]
+pub(crate) struct ArraySplitter {
+    buf: Vec<u8>,
+    pos: usize,
+    // Nesting level, where 1 is inside the top-level array
+    depth: u32,
+    in_string: bool,
+    escaped: bool,
+    element_start: Option<usize>,
+    done: bool,
+}
+
+impl ArraySplitter {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Whether the closing `]` of the array was seen.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// The next complete element, or `None` if more input is needed.
+    pub fn next_element(&mut self) -> Result<Option<Vec<u8>>, ApiError> {
+        while self.pos < self.buf.len() && !self.done {
+            let byte = self.buf[self.pos];
+            self.pos += 1;
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match (self.depth, byte) {
+                (_, b' ' | b'\t' | b'\r' | b'\n') => {}
+                (0, b'[') => self.depth = 1,
+                (0, _) => {
+                    return Err(ApiError::with_kind(
+                        Kind::Decode,
+                        format!(
+                            "expected a list of builds, got: {}",
+                            String::from_utf8_lossy(&self.buf)
+                        ),
+                    ))
+                }
+                (1, b',') if self.element_start.is_none() => {}
+                (1, b']') if self.element_start.is_none() => self.done = true,
+                (1, b'{' | b'[') => {
+                    self.element_start = Some(self.pos - 1);
+                    self.depth += 1;
+                }
+                (_, b'{' | b'[') => self.depth += 1,
+                (_, b'}' | b']') => {
+                    self.depth -= 1;
+                    if self.depth == 1 {
+                        let start = self.element_start.take().expect("element was started");
+                        let element = self.buf[start..self.pos].to_vec();
+                        self.buf.drain(..self.pos);
+                        self.pos = 0;
+                        return Ok(Some(element));
+                    }
+                }
+                (_, b'"') => self.in_string = true,
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
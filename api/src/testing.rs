@@ -0,0 +1,128 @@
+//! Stand-ins for [`SesiClient`](crate::SesiClient) in tests of code using this crate.
+
+use crate::{
+    ApiError, Build, BuildApi, BuildUrl, Kind, ListBuildsParms, Platform, Product, ReleaseChannel,
+};
+use std::future::Future;
+
+/// A [`BuildApi`] answering from canned builds and download URLs, without network access.
+///
+/// Listings are filtered like SideFX does: by product, by platform for products that have
+/// one, by version if given, and to gold builds for `only_production`.
+///
+/// ```
+/// use houdini_downloader_api::testing::MockClient;
+/// use houdini_downloader_api::{
+///     ApiError, Build, BuildApi, BuildUrl, Kind, ListBuildsParms, Platform, Product,
+/// };
+///
+/// /// Code under test: resolve the newest production build of a version.
+/// async fn newest(api: &impl BuildApi, version: &str) -> Result<BuildUrl, ApiError> {
+///     let parms = ListBuildsParms::builder().version(version).build();
+///     let builds = api.list_builds(parms).await?;
+///     let newest = builds.iter().max_by_key(|b| b.build).expect("no builds");
+///     api.get_build_url(newest.product, Platform::Linux, newest.version.clone(), newest.build)
+///         .await
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let build = |build| Build {
+///     build,
+///     version: "20.0".into(),
+///     platform: "linux_x86_64_gcc11.2".into(),
+///     release: "gold".into(),
+///     ..Build::default()
+/// };
+/// let url = BuildUrl {
+///     download_url: "https://example.com/houdini-20.0.547.tar.gz".into(),
+///     filename: "houdini-20.0.547.tar.gz".into(),
+///     hash: "f355bfe7271e0755908a3680f1f3c619".into(),
+///     size: 2_000_000_000,
+/// };
+/// let api = MockClient::new()
+///     .with_build(build(506))
+///     .with_build(build(547))
+///     .with_build_url(Product::Houdini, Platform::Linux, "20.0", 547, url);
+/// assert_eq!(newest(&api, "20.0").await.unwrap().filename, "houdini-20.0.547.tar.gz");
+///
+/// let missing = api.get_build_url(Product::Houdini, Platform::Linux, "20.0".into(), 506).await;
+/// assert_eq!(missing.unwrap_err().kind(), Kind::NotFound);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClient {
+    builds: Vec<Build>,
+    urls: Vec<(Product, Platform, String, u64, BuildUrl)>,
+}
+
+impl MockClient {
+    pub fn new() -> Self {
+        MockClient::default()
+    }
+
+    /// Add a build to the listings.
+    pub fn with_build(mut self, build: Build) -> Self {
+        self.builds.push(build);
+        self
+    }
+
+    /// Answer [`BuildApi::get_build_url`] for the build with `url`. Builds without one are
+    /// reported as not found, whether they're listed or not.
+    pub fn with_build_url(
+        mut self,
+        product: Product,
+        platform: Platform,
+        version: impl Into<String>,
+        build: u64,
+        url: BuildUrl,
+    ) -> Self {
+        self.urls
+            .push((product, platform, version.into(), build, url));
+        self
+    }
+}
+
+impl BuildApi for MockClient {
+    fn list_builds(
+        &self,
+        parms: ListBuildsParms,
+    ) -> impl Future<Output = Result<Vec<Build>, ApiError>> + Send {
+        let builds = self
+            .builds
+            .iter()
+            .filter(|b| {
+                b.product == parms.product
+                    && (!parms.product.requires_platform()
+                        || Platform::from_build_str(&b.platform) == parms.platform)
+                    && parms.version.as_ref().is_none_or(|v| &b.version == v)
+                    && (!parms.only_production || b.release_channel() == ReleaseChannel::Gold)
+            })
+            .cloned()
+            .collect();
+        std::future::ready(Ok(builds))
+    }
+
+    fn get_build_url(
+        &self,
+        product: Product,
+        platform: Platform,
+        version: String,
+        build: u64,
+    ) -> impl Future<Output = Result<BuildUrl, ApiError>> + Send {
+        let url = self
+            .urls
+            .iter()
+            .find(|(p, pl, v, b, _)| {
+                *p == product && *pl == platform && *v == version && *b == build
+            })
+            .map(|(.., url)| url.clone())
+            .ok_or_else(|| {
+                ApiError::with_kind(
+                    Kind::NotFound,
+                    format!("build {version}.{build} not found for {platform}"),
+                )
+            });
+        std::future::ready(url)
+    }
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use common::{temp_dir, MockServer, Response};
+use houdini_downloader_api::{ClientConfig, Kind, Platform, Product, RateLimitFn, SesiClient};
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn rate_limited_request_is_retried_after_the_wait() {
+    let limited = AtomicBool::new(false);
+    let server = MockServer::api(move |_| match limited.swap(true, Ordering::SeqCst) {
+        false => Response::new("429 Too Many Requests", "{}").header("Retry-After", "0"),
+        true => Response::json("[]"),
+    })
+    .await;
+    let waits = Arc::new(Mutex::new(Vec::new()));
+    let seen = waits.clone();
+    let config = ClientConfig {
+        on_rate_limit: Some(RateLimitFn(Arc::new(move |limited| {
+            seen.lock()
+                .unwrap()
+                .push((limited.wait.as_secs(), limited.retry_after));
+        }))),
+        ..server.config()
+    };
+    let client = SesiClient::with_config("id", "secret", config)
+        .await
+        .unwrap();
+    client
+        .list_builds(Product::Houdini, Platform::Linux, None::<String>, true)
+        .await
+        .unwrap();
+    assert_eq!(*waits.lock().unwrap(), [(0, true)]);
+}
+
+#[tokio::test]
+async fn extra_headers_go_with_token_and_api_requests() {
+    let server = MockServer::api(|_| Response::json("[]")).await;
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Gateway-Token", HeaderValue::from_static("secret"));
+    let config = ClientConfig {
+        headers,
+        ..server.config()
+    };
+    let client = SesiClient::with_config("id", "secret", config)
+        .await
+        .unwrap();
+    client
+        .list_builds(Product::Houdini, Platform::Linux, None::<String>, true)
+        .await
+        .unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests
+        .iter()
+        .all(|request| request.header("x-gateway-token") == Some("secret")));
+}
+
+#[tokio::test]
+async fn rejected_cached_token_is_replaced() {
+    let server = MockServer::start(|request| {
+        if request.is_token() {
+            Response::token("fresh")
+        } else if request.header("authorization") == Some("Bearer stale") {
+            Response::new("401 Unauthorized", r#"{"error": "invalid token"}"#)
+        } else {
+            Response::json("[]")
+        }
+    })
+    .await;
+    let cache = temp_dir("stale-token");
+    // Cached and unexpired, but revoked on the server
+    let stale = r#"{"access_token": "stale", "expires_in": 3600, "expires_at": 9999999999}"#;
+    std::fs::write(cache.join("api.token"), stale).unwrap();
+    let config = ClientConfig {
+        cache_dir: Some(cache.clone()),
+        token_cache: true,
+        ..server.config()
+    };
+    let client = SesiClient::with_config("id", "secret", config)
+        .await
+        .unwrap();
+    let builds = client
+        .list_builds(Product::Houdini, Platform::Linux, None::<String>, true)
+        .await
+        .unwrap();
+    assert!(builds.is_empty());
+    let cached = std::fs::read_to_string(cache.join("api.token")).unwrap();
+    assert!(cached.contains("fresh"));
+    std::fs::remove_dir_all(cache).unwrap();
+}
+
+#[tokio::test]
+async fn maintenance_page_is_a_request_error() {
+    let server = MockServer::start(|_| {
+        Response::ok("<html><body>Down for maintenance</body></html>")
+            .header("Content-Type", "text/html")
+    })
+    .await;
+    let config = ClientConfig {
+        api_retries: 0,
+        ..server.config()
+    };
+    let Err(e) = SesiClient::with_config("id", "secret", config).await else {
+        panic!("authorized with a maintenance page");
+    };
+    assert_eq!(e.kind(), Kind::Request);
+    assert!(e
+        .to_string()
+        .contains("body starts: <html><body>Down for maintenance"));
+}
+
+#[tokio::test]
+async fn shared_client_settings_apply() {
+    // A proxy that refuses every request
+    let proxy = MockServer::start(|_| Response::close()).await;
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(proxy.url("")).unwrap())
+        .build()
+        .unwrap();
+    let config = ClientConfig {
+        token_cache: false,
+        ..ClientConfig::default()
+    };
+    assert!(
+        SesiClient::with_client_and_config(client, "id", "secret", config)
+            .await
+            .is_err()
+    );
+    assert!(!proxy.requests().is_empty());
+}
+
+#[tokio::test]
+async fn missing_build_is_not_found() {
+    let server = MockServer::api(|request| match request.path.as_str() {
+        "/null" => Response::json("null"),
+        _ => Response::json(r#"{"error": "Build 20.0.999 does not exist"}"#),
+    })
+    .await;
+    for response in ["/null", "/error"] {
+        let config = ClientConfig {
+            api_url: server.url(response),
+            ..server.config()
+        };
+        let client = SesiClient::with_config("id", "secret", config)
+            .await
+            .unwrap();
+        let e = client
+            .get_build_url(Product::Houdini, Platform::Linux, "20.0", 999)
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), Kind::NotFound);
+        assert!(e
+            .to_string()
+            .ends_with("build 20.0.999 not found for linux"));
+    }
+}
+
+#[tokio::test]
+async fn build_urls_fail_on_their_own() {
+    let server = MockServer::api(|request| {
+        let body = String::from_utf8_lossy(&request.body).into_owned();
+        let query = format!("{}{body}", request.path);
+        if query.contains("%3A806") {
+            return Response::json("null");
+        }
+        let build = if query.contains("%3A805") { 805 } else { 807 };
+        Response::json(&format!(
+            r#"{{"download_url": "https://example.com/{build}",
+                "filename": "houdini-{build}.tar.gz", "hash": "", "size": {build}}}"#
+        ))
+    })
+    .await;
+    let client = SesiClient::with_config("id", "secret", server.config())
+        .await
+        .unwrap();
+    let urls = client
+        .get_build_urls(Product::Houdini, Platform::Linux, "19.5", [805, 806, 807])
+        .await;
+    assert_eq!(urls[0].as_ref().unwrap().filename, "houdini-805.tar.gz");
+    assert_eq!(urls[1].as_ref().unwrap_err().kind(), Kind::NotFound);
+    assert_eq!(urls[2].as_ref().unwrap().filename, "houdini-807.tar.gz");
+}
+
+#[tokio::test]
+async fn listed_build_resolves_with_its_own_parameters() {
+    let server = MockServer::api(|request| {
+        let call = request.api_call().unwrap();
+        if call[0] == "download.get_daily_builds_list" {
+            return Response::json(
+                r#"[{"build": "805", "product": "houdini", "platform": "linux_x86_64_gcc9.3",
+                    "version": "19.5"},
+                    {"build": "119", "product": "launcher-iso", "platform": "linux",
+                    "version": "2.0"}]"#,
+            );
+        }
+        Response::json(
+            r#"{"download_url": "https://example.com/build", "filename": "build",
+                "hash": "", "size": 1}"#,
+        )
+    })
+    .await;
+    let client = SesiClient::with_config("id", "secret", server.config())
+        .await
+        .unwrap();
+    let builds = client
+        .list_builds(Product::Houdini, Platform::Linux, None::<String>, true)
+        .await
+        .unwrap();
+    assert_eq!(builds.len(), 2);
+    for build in &builds {
+        client.get_build_url_for(build).await.unwrap();
+    }
+
+    let parms: Vec<_> = server
+        .requests()
+        .iter()
+        .filter_map(|request| request.api_call())
+        .filter(|call| call[0] == "download.get_daily_build_download")
+        .map(|call| call[2].clone())
+        .collect();
+    assert_eq!(
+        parms,
+        [
+            serde_json::json!({"product": "houdini", "platform": "linux", "version": "19.5",
+                "build": 805}),
+            // One launcher image for all platforms, so requests leave the platform out
+            serde_json::json!({"product": "launcher-iso", "version": "2.0", "build": 119}),
+        ]
+    );
+}
@@ -0,0 +1,255 @@
+//! A local HTTP server standing in for SideFX and its CDN, answering each request with what the
+//! test's handler returns and recording the requests for assertions.
+
+#![allow(dead_code)]
+
+use houdini_downloader_api::ClientConfig;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A request as the server received it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_token(&self) -> bool {
+        self.method == "POST" && self.path.split('?').next() == Some("/token")
+    }
+
+    /// The `[method, args, parms]` of an API call, from its `json` form field.
+    pub fn api_call(&self) -> Option<serde_json::Value> {
+        let form: Vec<(String, String)> = serde_urlencoded::from_bytes(&self.body).ok()?;
+        let (_, call) = form.into_iter().find(|(name, _)| name == "json")?;
+        serde_json::from_str(&call).ok()
+    }
+}
+
+enum Action {
+    Send,
+    /// Keep the connection open after sending, e.g. in the middle of a body.
+    Hang,
+    /// Close the connection without answering.
+    Close,
+}
+
+/// The `Content-Length` sent.
+enum Length {
+    Body,
+    Announced(usize),
+    /// None, the body ends when the connection closes.
+    Unknown,
+}
+
+/// What the server answers with.
+pub struct Response {
+    action: Action,
+    status: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    length: Length,
+}
+
+impl Response {
+    pub fn new(status: &str, body: impl Into<Vec<u8>>) -> Response {
+        Response {
+            action: Action::Send,
+            status: status.to_string(),
+            headers: Vec::new(),
+            body: body.into(),
+            length: Length::Body,
+        }
+    }
+
+    pub fn ok(body: impl Into<Vec<u8>>) -> Response {
+        Response::new("200 OK", body)
+    }
+
+    pub fn json(body: &str) -> Response {
+        Response::ok(body).header("Content-Type", "application/json")
+    }
+
+    /// An access token valid for an hour.
+    pub fn token(token: &str) -> Response {
+        Response::json(&format!(
+            r#"{{"access_token": "{token}", "expires_in": 3600}}"#
+        ))
+    }
+
+    /// Stall after sending: announce more than the body with [`Response::content_length`] to
+    /// stop in the middle of a download.
+    pub fn then_hang(mut self) -> Response {
+        self.action = Action::Hang;
+        self
+    }
+
+    pub fn close() -> Response {
+        Response {
+            action: Action::Close,
+            ..Response::ok("")
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Response {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Announce `len` bytes instead of the length of the body, e.g. to cut a download short.
+    pub fn content_length(mut self, len: usize) -> Response {
+        self.length = Length::Announced(len);
+        self
+    }
+
+    /// Send no `Content-Length` at all, the body ends when the connection closes.
+    pub fn without_content_length(mut self) -> Response {
+        self.length = Length::Unknown;
+        self
+    }
+}
+
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+pub struct MockServer {
+    pub addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    pub async fn start(
+        handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+    ) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, handler.clone(), seen.clone()));
+            }
+        });
+        MockServer { addr, requests }
+    }
+
+    /// A server handing out a token at `/token` and answering everything else with `handler`.
+    pub async fn api(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> MockServer {
+        MockServer::start(move |request| match request.is_token() {
+            true => Response::token("token"),
+            false => handler(request),
+        })
+        .await
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// A config sending the token request to `/token` and API calls to `/api`, without caches.
+    pub fn config(&self) -> ClientConfig {
+        ClientConfig {
+            token_url: self.url("/token"),
+            api_url: self.url("/api"),
+            token_cache: false,
+            listing_cache: false,
+            ..ClientConfig::default()
+        }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// An empty directory for the files of one test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("houdl-test-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn serve(mut stream: TcpStream, handler: Arc<Handler>, seen: Arc<Mutex<Vec<Request>>>) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let response = handler(&request);
+    seen.lock().unwrap().push(request);
+    if let Action::Close = response.action {
+        return;
+    }
+    let mut head = format!("HTTP/1.1 {}\r\nConnection: close\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    let len = match response.length {
+        Length::Body => Some(response.body.len()),
+        Length::Announced(len) => Some(len),
+        Length::Unknown => None,
+    };
+    if let Some(len) = len {
+        head.push_str(&format!("Content-Length: {len}\r\n"));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&response.body).await;
+    if let Action::Hang = response.action {
+        std::future::pending::<()>().await;
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut data = Vec::new();
+    let mut buffer = [0; 4096];
+    let head_end = loop {
+        if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buffer[..read]);
+    };
+    let head = String::from_utf8_lossy(&data[..head_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let (method, path) = (request_line.next()?, request_line.next()?);
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body: data[head_end + 4..].to_vec(),
+    };
+    let len: usize = request
+        .header("content-length")
+        .and_then(|len| len.parse().ok())
+        .unwrap_or(0);
+    while request.body.len() < len {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        request.body.extend_from_slice(&buffer[..read]);
+    }
+    Some(request)
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use common::{temp_dir, MockServer, Response};
+use houdini_downloader_api::download::{self, TransferOptions};
+use houdini_downloader_api::BuildUrl;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// md5 of "houdini".
+const HOUDINI_MD5: &str = "efdc8aa869642809b0f0132706218f0a";
+
+fn build_url(server: &MockServer, filename: &str, hash: &str, size: u64) -> BuildUrl {
+    BuildUrl {
+        download_url: server.url("/houdini.tar.gz"),
+        filename: filename.into(),
+        hash: hash.into(),
+        size,
+    }
+}
+
+#[tokio::test]
+async fn mismatched_hash_is_discarded() {
+    let server = MockServer::start(|_| Response::ok("houdini")).await;
+    let dir = temp_dir("mismatch");
+    let url = build_url(&server, "houdini.tar.gz", &"0".repeat(32), 7);
+    let (http, opts) = (reqwest::Client::new(), TransferOptions::default());
+    let outcome = download::download_build_to(&http, &url, &dir, &opts)
+        .await
+        .unwrap();
+    assert_eq!(outcome.bytes_written, 7);
+    assert!(outcome.size_matched);
+    assert!(!outcome.hash_matched);
+    assert_eq!(outcome.computed_hash.as_deref(), Some(HOUDINI_MD5));
+    assert!(!outcome.is_accepted());
+    assert!(!outcome.path.exists());
+    assert!(!dir.join("houdini.tar.gz.partial").exists());
+}
+
+#[tokio::test]
+async fn cancelled_download_removes_the_partial_file() {
+    // Half of the body, then nothing
+    let server = MockServer::start(|_| Response::ok("hou").content_length(7).then_hang()).await;
+    let dir = temp_dir("cancel");
+    let partial = dir.join("houdini.tar.gz.partial");
+    let url = build_url(&server, "houdini.tar.gz", "", 7);
+    let (http, opts) = (reqwest::Client::new(), TransferOptions::default());
+    tokio::select! {
+        result = download::download_build_to(&http, &url, &dir, &opts) => {
+            panic!("a stalled download finished: {result:?}");
+        }
+        started = tokio::time::timeout(Duration::from_secs(5), async {
+            while !partial.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }) => started.expect("the download never started writing"),
+    }
+    assert!(!partial.exists());
+    assert!(!dir.join("houdini.tar.gz").exists());
+}
+
+#[tokio::test]
+async fn truncated_body_fails_and_leaves_no_file() {
+    // Announces 10 bytes, sends 7 and closes the connection
+    let server = MockServer::start(|_| Response::ok("houdini").content_length(10)).await;
+    let dir = temp_dir("truncated");
+    let url = build_url(&server, "houdini.tar.gz", "", 10);
+    let opts = TransferOptions {
+        retries: 0,
+        ..TransferOptions::default()
+    };
+    let http = reqwest::Client::new();
+    let error = download::download_build_to(&http, &url, &dir, &opts)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("after 7 of 10 bytes"), "{error}");
+    assert!(!dir.join("houdini.tar.gz.partial").exists());
+    assert!(!dir.join("houdini.tar.gz").exists());
+}
+
+#[tokio::test]
+async fn dropped_range_is_resumed() {
+    const CONTENT: &[u8] = b"houdini-20.0.547";
+    let dropped = AtomicBool::new(false);
+    let server = MockServer::start(move |request| {
+        let range = request
+            .header("range")
+            .and_then(|range| range.strip_prefix("bytes="));
+        let Some((start, end)) = range.and_then(|range| range.split_once('-')) else {
+            return Response::ok(CONTENT);
+        };
+        let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+        let response = Response::new("206 Partial Content", &CONTENT[start..=end]).header(
+            "Content-Range",
+            &format!("bytes {start}-{end}/{}", CONTENT.len()),
+        );
+        // The first full request of the second range stops after 3 bytes
+        if start == 8 && !dropped.swap(true, Ordering::SeqCst) {
+            return Response::new("206 Partial Content", &CONTENT[start..start + 3])
+                .content_length(end - start + 1);
+        }
+        response
+    })
+    .await;
+    let dir = temp_dir("ranges");
+    let url = build_url(&server, "houdini.tar.gz", "", CONTENT.len() as u64);
+    let opts = TransferOptions {
+        connections: 2,
+        retries: 1,
+        ..TransferOptions::default()
+    };
+    let http = reqwest::Client::new();
+    let outcome = download::download_build_to(&http, &url, &dir, &opts)
+        .await
+        .unwrap();
+    assert!(outcome.is_accepted());
+    assert_eq!(std::fs::read(&outcome.path).unwrap(), CONTENT);
+    let ranges: Vec<_> = server
+        .requests()
+        .iter()
+        .filter_map(|request| request.header("range").map(str::to_string))
+        .collect();
+    assert!(ranges.contains(&"bytes=11-15".to_string()), "{ranges:?}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn html_page_fails_before_writing() {
+    let server = MockServer::start(|_| {
+        Response::ok("<html><body>Link expired</body></html>")
+            .header("Content-Type", "text/html; charset=utf-8")
+    })
+    .await;
+    let dir = temp_dir("html");
+    let url = build_url(&server, "houdini.tar.gz", &"0".repeat(32), 2_000_000_000);
+    let (http, opts) = (reqwest::Client::new(), TransferOptions::default());
+    let error = download::download_build_to(&http, &url, &dir, &opts)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("text/html"));
+    assert!(!dir.join("houdini.tar.gz.partial").exists());
+}
+
+#[tokio::test]
+async fn download_to_bytes_checks_the_size() {
+    let server = MockServer::start(|_| Response::ok("houdini").without_content_length()).await;
+    let url = build_url(&server, "launcher.json", HOUDINI_MD5, 0);
+    let http = reqwest::Client::new();
+    let (content, hash) = download::download_to_bytes(&http, &url, 1024)
+        .await
+        .unwrap();
+    assert_eq!(&content[..], b"houdini");
+    assert_eq!(hash, url.hash);
+
+    let error = download::download_to_bytes(&http, &url, 4)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("more than 4 bytes"));
+}
+
+#[tokio::test]
+async fn remote_size_of_a_server_ignoring_ranges() {
+    let server = MockServer::start(|_| Response::ok("houdini")).await;
+    let url: BuildUrl = serde_json::from_value(serde_json::json!({
+        "download_url": server.url("/houdini.tar.gz"),
+        "filename": "houdini.tar.gz",
+        "hash": HOUDINI_MD5,
+        "size": null,
+    }))
+    .unwrap();
+    assert_eq!(url.size, 0);
+    let http = reqwest::Client::new();
+    assert_eq!(download::remote_size(&http, &url).await, Some(7));
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{temp_dir, MockServer, Request, Response};
+use houdini_downloader_api::download::{self, TransferOptions};
+use houdini_downloader_api::{
+    BuildUrl, ClientConfig, Platform, Product, SesiClient, DEFAULT_USER_AGENT,
+};
+
+/// Authorize, list builds and download one with the client of `config`, and then without
+/// authorizing with the download client of `config`, returning the requests sent.
+async fn requests_with(config: impl Fn(&MockServer) -> ClientConfig) -> Vec<Request> {
+    let server = MockServer::api(|request| match request.path.as_str() {
+        "/houdini.tar.gz" => Response::ok("houdini"),
+        _ => Response::json("[]"),
+    })
+    .await;
+    let url = BuildUrl {
+        download_url: server.url("/houdini.tar.gz"),
+        filename: "houdini.tar.gz".into(),
+        hash: String::new(),
+        size: 7,
+    };
+    let dir = temp_dir(&format!("user-agent-{}", server.addr.port()));
+    let opts = TransferOptions::default();
+
+    let client = SesiClient::with_config("id", "secret", config(&server))
+        .await
+        .unwrap();
+    client
+        .list_builds(Product::Houdini, Platform::Linux, None::<String>, true)
+        .await
+        .unwrap();
+    download::download_build_to(client.http_client(), &url, &dir, &opts)
+        .await
+        .unwrap();
+
+    let http = config(&server).http_client().unwrap();
+    download::download_build_to(&http, &url, &dir, &opts)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[0].is_token());
+    assert_eq!(requests[1].path, "/api");
+    assert_eq!(requests[2].path, "/houdini.tar.gz");
+    assert_eq!(requests[3].path, "/houdini.tar.gz");
+    requests
+}
+
+#[tokio::test]
+async fn requests_identify_the_downloader() {
+    assert!(DEFAULT_USER_AGENT.starts_with("houdini-downloader/"));
+    for request in requests_with(MockServer::config).await {
+        assert_eq!(request.header("user-agent"), Some(DEFAULT_USER_AGENT));
+    }
+}
+
+#[tokio::test]
+async fn user_agent_can_be_replaced() {
+    let config = |server: &MockServer| ClientConfig {
+        user_agent: "studio-mirror/1.0".into(),
+        ..server.config()
+    };
+    for request in requests_with(config).await {
+        assert_eq!(request.header("user-agent"), Some("studio-mirror/1.0"));
+    }
+}
@@ -1,7 +1,13 @@
+use crate::template::Template;
+use anyhow::{bail, Context};
 use clap::{Parser, Subcommand, ValueEnum};
-use houdini_downloader_api::{Platform, Product};
-use std::ops::Not;
-use std::path::PathBuf;
+use houdini_downloader_api::{
+    netrc, Build, BuildOrder, HashAlgo, Platform, Product, ReleaseChannel, Version,
+};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 /// Utility for downloading SideFX Houdini installers and ISO images.
 #[derive(Debug, Parser)]
@@ -14,15 +20,140 @@ pub struct Args {
     pub user_id: Option<String>,
     #[arg(long, global = true, env = "SESI_USER_SECRET", hide_env_values = true)]
     pub user_secret: Option<String>,
+    /// Read the user id and secret from stdin instead, keeping them out of the shell history
+    /// and process list: `user_id=<id>` and `user_secret=<secret>` lines, or the id on the
+    /// first line and the secret on the second. Implies --auto-confirm, stdin is taken.
+    #[arg(long, global = true)]
+    pub credentials_from_stdin: bool,
+    /// The `.netrc` file the credentials were read from, if neither was given otherwise.
+    #[arg(skip)]
+    pub netrc: Option<PathBuf>,
+    /// Whether the credentials were read from the OS keychain, see `login`.
+    #[arg(skip)]
+    pub keyring: bool,
     #[arg(long, global = true, value_enum, default_value_t = ProductArg::Houdini)]
     pub product: ProductArg,
     #[arg(long, global = true, value_enum, default_value_t = PlatformArg::default())]
     pub platform: PlatformArg,
+    /// Advanced: exact SideFX platform identifier to send instead of --platform. Not validated.
+    #[arg(long, global = true, conflicts_with = "platform")]
+    pub platform_raw: Option<String>,
+    /// Override the User-Agent header sent to SideFX.
+    #[arg(long, global = true)]
+    pub user_agent: Option<String>,
+    /// Extra header sent with the token request and API calls, e.g. for an API gateway. Can be
+    /// repeated [e.g. 'X-Gateway-Token: abc']. Not sent with downloads.
+    #[arg(long = "header", global = true, value_name = "NAME: VALUE", value_parser = parse_header)]
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    /// Maximum number of concurrent API requests.
+    #[arg(long, global = true, default_value_t = 4)]
+    pub jobs: usize,
+    /// Number of retries when SideFX rate-limits an API request.
+    #[arg(long, global = true, default_value_t = 3)]
+    pub api_retries: u32,
+    /// Don't validate TLS certificates. Insecure, only for proxies that break the certificate chain.
+    #[arg(long, global = true)]
+    pub insecure: bool,
+    /// Additional PEM root certificate to trust, e.g. of a corporate proxy. Can be repeated.
+    #[arg(long, global = true)]
+    pub cacert: Vec<PathBuf>,
+    /// Directory for the access token and listing cache [default: $HOUDINI_DOWNLOADER_CACHE_DIR or the system cache directory].
+    #[arg(long, global = true, value_parser = expand_path)]
+    pub cache_dir: Option<PathBuf>,
+    /// Don't read or save the cached access token and build listings.
+    #[arg(long, global = true, conflicts_with = "cache_dir")]
+    pub no_cache: bool,
+    /// Print API requests and responses to stderr, with credentials redacted.
+    #[arg(long, global = true)]
+    pub trace_http: bool,
+    /// Maximum number of redirects to follow, 0 to not follow any.
+    #[arg(long, global = true, default_value_t = 10)]
+    pub max_redirects: usize,
+    /// Idle connections kept open per host for reuse [default: no limit].
+    #[arg(long, global = true, value_name = "N")]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Seconds an idle connection is kept open for reuse [default: 90].
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub pool_idle_timeout: Option<u64>,
+    /// Only use HTTP/1.1, for proxies that hang or break connections with HTTP/2.
+    #[arg(long, global = true)]
+    pub http1_only: bool,
+    /// Print the effective settings after merging the command line, environment and `.netrc`,
+    /// then exit without contacting SideFX. The credentials are only shown by their source.
+    /// The format goes after an `=`, like `--dump-config=json`.
+    #[arg(long, global = true, value_enum, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "text")]
+    pub dump_config: Option<ConfigFormat>,
+    /// Don't color tables. Colors are also left out when stdout isn't a terminal or NO_COLOR is set.
+    #[arg(long, global = true, env = "NO_COLOR")]
+    pub no_color: bool,
 }
 
 impl Args {
+    /// Parse the command line, falling back to the credentials stored with `login` and then
+    /// to the `sidefx.com` entry of `~/.netrc`.
     pub fn parse_() -> Self {
-        Args::parse()
+        let mut args = Args::parse();
+        if args.user_id.is_none() && args.user_secret.is_none() && !args.credentials_from_stdin {
+            #[cfg(feature = "keyring")]
+            if let Some((user_id, user_secret)) = crate::keyring::load() {
+                args.user_id = Some(user_id);
+                args.user_secret = Some(user_secret);
+                args.keyring = true;
+                return args;
+            }
+            if let Some((user_id, user_secret)) = netrc::credentials() {
+                args.user_id = Some(user_id);
+                args.user_secret = Some(user_secret);
+                args.netrc = netrc::path();
+            }
+        }
+        args
+    }
+
+    /// Replace the credentials with the ones piped to stdin, see `--credentials-from-stdin`.
+    pub fn read_credentials_from_stdin(&mut self) -> anyhow::Result<()> {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Could not read the credentials from stdin")?;
+        let (user_id, user_secret) = parse_credentials(&input)?;
+        self.user_id = Some(user_id);
+        self.user_secret = Some(user_secret);
+        self.netrc = None;
+        self.keyring = false;
+        Ok(())
+    }
+
+    /// Where `value`, the user id or secret, came from. `env` is its environment variable.
+    pub fn credential_source(&self, value: &Option<String>, env: &str) -> Option<String> {
+        match (value, &self.netrc) {
+            (None, _) => None,
+            (Some(_), Some(netrc)) => Some(netrc.to_string_lossy().into_owned()),
+            (Some(_), None) if self.credentials_from_stdin => Some("stdin".to_string()),
+            (Some(_), None) if self.keyring => Some("keychain".to_string()),
+            (Some(value), None) if std::env::var(env).is_ok_and(|env| &env == value) => {
+                Some("environment".to_string())
+            }
+            (Some(_), None) => Some("command line".to_string()),
+        }
+    }
+
+    /// The options of the download commands.
+    pub fn download(&self) -> Option<&DownloadArgs> {
+        match &self.commands {
+            Commands::Get { download, .. } | Commands::DownloadUrl { download, .. } => {
+                Some(download)
+            }
+            _ => None,
+        }
+    }
+
+    /// The platform to query, honoring --platform-raw.
+    pub fn platform(&self) -> anyhow::Result<Platform> {
+        match &self.platform_raw {
+            Some(raw) => Ok(Platform::Other(raw.clone())),
+            None => self.platform.try_into(),
+        }
     }
 }
 
@@ -30,50 +161,489 @@ impl Args {
 pub enum Commands {
     /// Download a particular build.
     Get {
-        /// Product version [e.g. 19.5]
-        #[arg(short, long)]
-        version: String,
+        /// Product version [e.g. 19.5], can include the build instead of --build [e.g. 19.5.805].
+        /// Only the major version [e.g. 19] picks its newest minor version.
+        #[arg(short, long, required_unless_present = "latest", value_parser = parse_version_arg)]
+        version: Option<VersionArg>,
 
-        /// Product build number.
-        #[arg(short, long)]
-        build: u64,
+        /// Product build number, a range [e.g. 500-510] or a comma separated list [e.g. 500,503,507].
+        #[arg(
+            short,
+            long,
+            value_parser = parse_build_list,
+            conflicts_with = "latest"
+        )]
+        build: Option<BuildList>,
+
+        /// Download the latest production build of the version, or of the newest version if none is given.
+        #[arg(long)]
+        latest: bool,
 
-        /// Directory to save the downloaded file.
-        #[arg(short, long, default_value_os_t = PathBuf::from("."))]
-        output_dir: PathBuf,
+        /// With --latest, pick the newest build marked good, skipping newer ones marked bad.
+        #[arg(long)]
+        prefer_good: bool,
 
-        /// Auto-confirm download and hide progress bar.
-        #[arg(short, long)]
-        silent: bool,
+        /// Release channel to pick the --latest build from. Beta builds are looked up among daily builds too.
+        #[arg(long, value_enum, default_value_t = ChannelArg::Any)]
+        channel: ChannelArg,
+
+        #[command(flatten)]
+        download: DownloadArgs,
 
-        /// Overwrite if file exists in the output directory.
+        /// Print the resolved download URL as JSON instead of downloading.
         #[arg(long)]
-        overwrite: bool,
+        dry_run: bool,
+
+        /// Allow downloading a build marked "bad" by SideFX with --silent or --auto-confirm.
+        #[arg(long)]
+        allow_bad: bool,
+
+        /// Allow selecting more than 10 builds with --build.
+        #[arg(long)]
+        yes: bool,
+
+        /// Keep going when a build of a batch fails and report all failures at the end.
+        #[arg(long)]
+        continue_on_error: bool,
+
+        /// Save the build info as JSON next to each downloaded installer [<filename>.json].
+        #[arg(long)]
+        write_metadata: bool,
+
+        /// Only download builds newer than the last one downloaded into the output directory
+        /// for the version, as recorded in its `.last-build` file.
+        #[arg(long)]
+        if_newer: bool,
+    },
+    /// Download a build from the JSON printed by `get --dry-run`, without credentials.
+    DownloadUrl {
+        /// File with the download URL JSON. Read from stdin if omitted.
+        #[arg(long)]
+        url_file: Option<PathBuf>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
     },
     /// List available builds.
     List {
         /// By default, only production builds are listed.
         #[arg(short, long, default_value_t = false)]
         include_daily_builds: bool,
-        /// Optional product version [e.g. 19.5]. By default all versions are listed.
+        /// Optional product version [e.g. 19.5, or 19 for its newest minor version].
+        /// By default all versions are listed.
+        #[arg(short, long, value_parser = parse_version_arg)]
+        version: Option<VersionArg>,
+        /// Also show the download size of each build (one extra request per build).
+        #[arg(long)]
+        with_size: bool,
+        /// List builds for all platforms instead of only --platform.
+        #[arg(long)]
+        all_platforms: bool,
+        /// List builds for all platforms, each build once with the platforms it's available on.
+        #[arg(long, conflicts_with = "with_size")]
+        group_by_build: bool,
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Print each build with this format instead [e.g. '{full_version} {status}'].
+        /// Placeholders: build, date, full_version, platform, product, python, release, status
+        /// and version. `{{` and `}}` are literal braces.
+        #[arg(long, value_parser = Template::parse, conflicts_with_all = ["format", "with_size", "group_by_build"])]
+        template: Option<Template>,
+        /// Sort builds by this, newest first. [default: version]
+        /// Without it NDJSON is printed in the order SideFX sends builds, as they arrive.
+        #[arg(long, value_enum)]
+        sort: Option<SortArg>,
+        /// List oldest first, after --limit picked the newest.
+        #[arg(long)]
+        reverse: bool,
+        /// Show dates as "3 days ago" in the text table. JSON and CSV keep the date as is.
+        #[arg(long)]
+        relative_dates: bool,
+        /// Only list builds of this release channel.
+        #[arg(long, value_enum, default_value_t = ChannelArg::Any)]
+        channel: ChannelArg,
+        /// Only list builds newer than this build number (exclusive).
+        #[arg(long)]
+        since_build: Option<u64>,
+        /// Only list builds up to this build number (inclusive).
+        #[arg(long)]
+        until_build: Option<u64>,
+        /// Only list builds whose platform names this Python version [e.g. 3.9].
+        #[arg(long)]
+        python: Option<String>,
+        /// Show only the N most recent builds, 0 for all.
+        /// [default: 25 for the text listing with --include-daily-builds, otherwise all]
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Show credential, token cache and platform information for troubleshooting.
+    Info,
+    /// Check the cache directory, the connection to SideFX, the credentials and the API, in that order.
+    Doctor,
+    /// Print the newest available product version.
+    LatestVersion {
+        /// By default, only production builds are considered.
+        #[arg(short, long, default_value_t = false)]
+        include_daily_builds: bool,
+    },
+    /// Compare the date, platform, status and release of two builds of a version.
+    Diff {
+        /// Product version [e.g. 19.5, or 19 for its newest minor version].
+        #[arg(short, long, value_parser = parse_version_arg)]
+        version: VersionArg,
+        /// Build to compare from.
+        #[arg(long)]
+        from_build: u64,
+        /// Build to compare to.
+        #[arg(long)]
+        to_build: u64,
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Download a generated file from a local server to check the download pipeline works.
+    #[command(hide = true)]
+    SelfTest {
+        /// Hide the progress bars.
         #[arg(short, long)]
-        version: Option<String>,
+        silent: bool,
+    },
+    /// Store the user id and secret in the OS keychain, asking for them unless
+    /// --credentials-from-stdin is given. Used when no credentials are given otherwise.
+    /// macOS and Linux only.
+    #[cfg(feature = "keyring")]
+    Login,
+    /// Remove the credentials stored with `login` from the OS keychain.
+    #[cfg(feature = "keyring")]
+    Logout,
+    /// List the products --product accepts and their SideFX identifiers.
+    Products {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// List the product versions that have builds, including daily builds.
+    Versions {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
 }
 
-impl Commands {
-    /// Verify if version is major.minor. None consider valid
-    pub fn is_version_valid(&self) -> bool {
-        let version_opt = match self {
-            Commands::Get { version, .. } => Some(version),
-            Commands::List { version, .. } => version.as_ref(),
-        };
-        if let Some(version) = version_opt {
-            version.ends_with('.').not() && version.split('.').count() == 2
+/// Options shared by the commands that download a build.
+#[derive(Debug, clap::Args)]
+pub struct DownloadArgs {
+    /// Directory to save the downloaded file. `~`, `$VAR`, `${VAR}` and on Windows `%VAR%` are expanded.
+    #[arg(short, long, default_value_os_t = PathBuf::from("."), value_parser = expand_path)]
+    pub output_dir: PathBuf,
+
+    /// Auto-confirm download and hide progress bar, same as --auto-confirm --no-progress.
+    #[arg(short, long)]
+    pub silent: bool,
+
+    /// Download without asking for confirmation.
+    #[arg(long)]
+    pub auto_confirm: bool,
+
+    /// Hide the progress bar.
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Print one line per download and nothing else, like
+    /// `OK houdini-20.0.547.tar.gz 1.87GiB md5=<hash> 42.3s` or `FAIL <filename> <time> <error>`.
+    /// Implies --silent; warnings still go to stderr.
+    #[arg(long)]
+    pub summary_only: bool,
+
+    /// Write JSON progress events to this file descriptor, for programs wrapping houdl.
+    /// Independent of the progress bar, e.g. `--progress-fd 3 --no-progress`.
+    #[arg(long, value_name = "FD", conflicts_with = "external")]
+    pub progress_fd: Option<i32>,
+
+    /// Print `<hash>  <path>` of each file in place to stdout, the line `md5sum -c -` reads.
+    /// Nothing else is printed to stdout.
+    #[arg(long, value_name = "ALGO", num_args = 0..=1, default_missing_value = "md5")]
+    pub print_checksum: Option<ChecksumArg>,
+
+    /// Overwrite if file exists in the output directory.
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Insert the platform into the saved filename [e.g. houdini-20.0.547-linux.tar.gz], to keep
+    /// downloads for several platforms apart in one directory.
+    #[arg(long)]
+    pub append_platform_to_filename: bool,
+
+    /// Prepend to the saved filename [e.g. `prod_` for prod_houdini-20.0.547.tar.gz].
+    #[arg(long, value_name = "PREFIX", value_parser = parse_filename_part)]
+    pub filename_prefix: Option<String>,
+
+    /// Insert before the extension of the saved filename, after the platform of
+    /// --append-platform-to-filename [e.g. `_2024-05` for houdini-20.0.547_2024-05.tar.gz].
+    #[arg(long, value_name = "SUFFIX", value_parser = parse_filename_part)]
+    pub filename_suffix: Option<String>,
+
+    /// Keep a file already in the output directory if its size and checksum match the build,
+    /// download it again otherwise.
+    #[arg(long, conflicts_with = "overwrite")]
+    pub prefer_cached: bool,
+
+    /// Size in bytes of the write buffer. Fewer, larger writes noticeably
+    /// speed up multi-gigabyte downloads to network filesystems.
+    #[arg(long, default_value_t = 1024 * 1024)]
+    pub buffer_size: usize,
+
+    /// Download over this many parallel connections, if the server supports range requests.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=64))]
+    pub connections: u16,
+
+    /// Hand the transfer to an installed download manager instead. The result is still verified.
+    #[arg(long, value_enum, conflicts_with_all = ["connections", "max_speed", "retry_download"])]
+    pub external: Option<ExternalDownloader>,
+
+    /// Resume the download this many times if the connection drops.
+    #[arg(long, default_value_t = 3)]
+    pub retry_download: u32,
+
+    /// Skip the checksum verification and only check the size. Faster on very large files,
+    /// but a corrupted download goes unnoticed.
+    #[arg(long, conflicts_with_all = ["prefer_cached", "external"])]
+    pub no_verify: bool,
+
+    /// Keep the partial file of a failed or interrupted download and continue it on the next run.
+    #[arg(long, conflicts_with = "external")]
+    pub keep_partial: bool,
+
+    /// Disk space to leave free in the output directory [e.g. 10G], 0 to leave none.
+    #[arg(long, value_parser = parse_byte_size)]
+    pub min_free_space: Option<u64>,
+
+    /// Limit the average download speed, in bytes per second [e.g. 500K, 5M].
+    #[arg(long, value_parser = parse_speed)]
+    pub max_speed: Option<u64>,
+
+    /// Append a JSON record per download, finished or failed, to this file.
+    #[arg(long, value_parser = expand_path)]
+    pub log_json: Option<PathBuf>,
+
+    /// Unpack a downloaded launcher ISO into a directory next to it, named like the image
+    /// without `.iso`. The image is verified first and kept.
+    #[cfg(feature = "iso")]
+    #[arg(long, conflicts_with = "no_verify")]
+    pub extract_iso: bool,
+
+    /// Show a desktop notification when the download finishes.
+    #[arg(long)]
+    pub notify: bool,
+
+    /// Open the folder containing the downloaded file when done.
+    #[arg(long)]
+    pub reveal: bool,
+}
+
+/// Expand a leading `~` to the home directory and `$VAR`, `${VAR}` or, on Windows, `%VAR%` to
+/// environment variables. Like in cmd.exe, an unset `%VAR%` is left as it is.
+fn expand_path(value: &str) -> Result<PathBuf, String> {
+    fn var(name: &str) -> Result<String, String> {
+        std::env::var(name).map_err(|_| format!("environment variable {name} is not set"))
+    }
+
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    if let Some(after) = rest.strip_prefix('~') {
+        if after.is_empty() || after.starts_with(['/', '\\']) {
+            let home = dirs::home_dir().ok_or("could not find the home directory")?;
+            expanded.push_str(&home.to_string_lossy());
+            rest = after;
+        }
+    }
+    let starts: &[char] = if cfg!(windows) { &['$', '%'] } else { &['$'] };
+    while let Some(i) = rest.find(starts) {
+        expanded.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if rest[i..].starts_with('%') {
+            match after.split_once('%') {
+                Some((name, remainder)) if !name.is_empty() => {
+                    match std::env::var(name) {
+                        Ok(value) => expanded.push_str(&value),
+                        Err(_) => expanded.push_str(&format!("%{name}%")),
+                    }
+                    rest = remainder;
+                }
+                _ => {
+                    expanded.push('%');
+                    rest = after;
+                }
+            }
+            continue;
+        }
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced.find('}').ok_or("unterminated ${ in path")?;
+            (&braced[..end], &braced[end + 1..])
         } else {
-            true
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if end == 0 {
+                expanded.push('$');
+                rest = after;
+                continue;
+            }
+            (&after[..end], &after[end..])
+        };
+        expanded.push_str(&var(name)?);
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(PathBuf::from(expanded))
+}
+
+/// The user id and secret from `user_id=`/`user_secret=` lines (`SESI_USER_ID=` and
+/// `SESI_USER_SECRET=` work too), or else the first two non-empty lines.
+fn parse_credentials(input: &str) -> anyhow::Result<(String, String)> {
+    let lines: Vec<&str> = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let value = |keys: [&str; 2]| {
+        lines.iter().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            keys.iter()
+                .any(|k| key.trim().eq_ignore_ascii_case(k))
+                .then(|| value.trim().to_string())
+        })
+    };
+    let credentials = match (
+        value(["user_id", "SESI_USER_ID"]),
+        value(["user_secret", "SESI_USER_SECRET"]),
+    ) {
+        (Some(user_id), Some(user_secret)) => (user_id, user_secret),
+        (None, None) if lines.len() == 2 => (lines[0].to_string(), lines[1].to_string()),
+        _ => bail!(
+            "Expected user_id=<id> and user_secret=<secret> lines on stdin, \
+            or the id and the secret on two lines"
+        ),
+    };
+    if credentials.0.is_empty() || credentials.1.is_empty() {
+        bail!("Empty user id or secret on stdin");
+    }
+    Ok(credentials)
+}
+
+/// Parse a `Name: Value` header.
+fn parse_header(value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or("expected a header like 'Name: Value'")?;
+    let name =
+        HeaderName::try_from(name.trim()).map_err(|e| format!("invalid header name: {e}"))?;
+    let value =
+        HeaderValue::try_from(value.trim()).map_err(|e| format!("invalid header value: {e}"))?;
+    Ok((name, value))
+}
+
+/// A `--version`: a [`Version`], or only the major version, which stands for the newest minor
+/// version SideFX has builds of.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VersionArg {
+    Exact(Version),
+    Major(u32),
+}
+
+impl VersionArg {
+    /// The build number of `major.minor.build`.
+    pub fn build(&self) -> Option<u64> {
+        match self {
+            VersionArg::Exact(version) => version.build,
+            VersionArg::Major(_) => None,
+        }
+    }
+}
+
+/// `19` is a major version, `19.5` and `19.5.805` go through [`Version`]'s parser.
+fn parse_version_arg(value: &str) -> Result<VersionArg, String> {
+    if let Ok(major) = value.trim().parse() {
+        return Ok(VersionArg::Major(major));
+    }
+    match value.parse::<Version>() {
+        Ok(version) => Ok(VersionArg::Exact(version)),
+        Err(e) => Err(format!("{e}, or only the major version [e.g. 19]")),
+    }
+}
+
+/// Part of a filename, which can't move the file to another directory.
+fn parse_filename_part(value: &str) -> Result<String, String> {
+    if value.contains(['/', '\\']) {
+        return Err("must not contain path separators".into());
+    }
+    Ok(value.to_string())
+}
+
+/// Parse a byte count with an optional binary suffix: K, M or G.
+fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size {value:?}, expected e.g. 100K, 5M or 1G"))?;
+    if number < 0.0 {
+        return Err("size must not be negative".to_string());
+    }
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// A byte count like [`parse_byte_size`], but a speed of 0 would never finish.
+fn parse_speed(value: &str) -> Result<u64, String> {
+    match parse_byte_size(value)? {
+        0 => Err("speed must be positive".to_string()),
+        speed => Ok(speed),
+    }
+}
+
+/// Builds selected with `get --build`, sorted and without duplicates.
+#[derive(Clone, Debug)]
+pub struct BuildList(pub Vec<u64>);
+
+impl BuildList {
+    /// Selections larger than this need `--yes`.
+    pub const CONFIRM_ABOVE: usize = 10;
+}
+
+/// Parse `500`, `500-510` or `500,503,507` (ranges are inclusive and can be mixed into lists).
+fn parse_build_list(value: &str) -> Result<BuildList, String> {
+    const MAX_BUILDS: u64 = 1000;
+    let parse = |number: &str| {
+        number
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("invalid build number {number:?}"))
+    };
+    let mut builds = Vec::new();
+    for part in value.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("build range {start}-{end} is reversed"));
+                }
+                if end - start >= MAX_BUILDS {
+                    return Err(format!("build range {start}-{end} is too large"));
+                }
+                builds.extend(start..=end);
+            }
+            None => builds.push(parse(part)?),
         }
     }
+    builds.sort_unstable();
+    builds.dedup();
+    Ok(BuildList(builds))
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
@@ -83,8 +653,118 @@ pub enum ProductArg {
     HoudiniLauncher,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+    /// One JSON object per line, printed as builds are received.
+    Ndjson,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ChannelArg {
+    Gold,
+    Beta,
+    Any,
+}
+
+impl ChannelArg {
+    pub fn matches(&self, build: &Build) -> bool {
+        match self {
+            ChannelArg::Gold => build.release_channel() == ReleaseChannel::Gold,
+            ChannelArg::Beta => build.release_channel() == ReleaseChannel::Beta,
+            ChannelArg::Any => true,
+        }
+    }
+}
+
+/// Keys `list --sort` orders builds by.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum SortArg {
+    Build,
+    Date,
+    Version,
+}
+
+impl SortArg {
+    pub fn order(&self) -> BuildOrder {
+        match self {
+            SortArg::Build => BuildOrder::Build,
+            SortArg::Date => BuildOrder::Date,
+            SortArg::Version => BuildOrder::Version,
+        }
+    }
+}
+
+/// How `--dump-config` prints the settings.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ConfigFormat {
+    Text,
+    Json,
+}
+
+/// Algorithms `--print-checksum` can compute.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ChecksumArg {
+    Md5,
+}
+
+impl ChecksumArg {
+    pub fn algo(&self) -> HashAlgo {
+        match self {
+            ChecksumArg::Md5 => HashAlgo::Md5,
+        }
+    }
+}
+
+/// Download managers `--external` knows the arguments of.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ExternalDownloader {
+    Aria2c,
+    Wget,
+    Curl,
+}
+
+impl ExternalDownloader {
+    /// Command saving `url` to `output`, without progress output if `silent`.
+    pub fn command(&self, url: &str, output: &Path, silent: bool) -> Command {
+        let mut command;
+        match self {
+            ExternalDownloader::Aria2c => {
+                command = Command::new("aria2c");
+                let dir = output.parent().unwrap_or(Path::new("."));
+                let file = output.file_name().unwrap_or_default();
+                command.arg("-d").arg(dir).arg("-o").arg(file);
+            }
+            ExternalDownloader::Wget => {
+                command = Command::new("wget");
+                command.arg("-O").arg(output);
+            }
+            ExternalDownloader::Curl => {
+                command = Command::new("curl");
+                command
+                    .arg("--fail")
+                    .arg("--location")
+                    .arg("-o")
+                    .arg(output);
+            }
+        }
+        if silent {
+            command.arg(match self {
+                ExternalDownloader::Aria2c | ExternalDownloader::Wget => "--quiet",
+                ExternalDownloader::Curl => "--silent",
+            });
+        }
+        command.arg(url);
+        command
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
 pub enum PlatformArg {
+    /// The platform of this machine.
+    Auto,
     Linux,
     Win64,
     Macos,
@@ -92,17 +772,14 @@ pub enum PlatformArg {
 }
 
 impl Default for PlatformArg {
+    /// The detected platform, so `--help` shows which one it is.
     fn default() -> Self {
-        if cfg!(target_os = "windows") {
-            PlatformArg::Win64
-        } else if cfg!(target_os = "linux") {
-            PlatformArg::Linux
-        } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
-            PlatformArg::Macos
-        } else if cfg!(all(target_os = "macos", target_os = "aarch64")) {
-            PlatformArg::MacosxArm64
-        } else {
-            panic!("Unsupported platform");
+        match Platform::current() {
+            Some(Platform::Win64) => PlatformArg::Win64,
+            Some(Platform::Linux) => PlatformArg::Linux,
+            Some(Platform::Macos) => PlatformArg::Macos,
+            Some(Platform::MacosxArm64) => PlatformArg::MacosxArm64,
+            _ => PlatformArg::Auto,
         }
     }
 }
@@ -117,13 +794,48 @@ impl From<ProductArg> for Product {
     }
 }
 
-impl From<PlatformArg> for Platform {
-    fn from(arg: PlatformArg) -> Self {
-        match arg {
+impl TryFrom<PlatformArg> for Platform {
+    type Error = anyhow::Error;
+
+    /// Fails for `auto` on an OS SideFX has no builds for.
+    fn try_from(arg: PlatformArg) -> anyhow::Result<Self> {
+        Ok(match arg {
+            PlatformArg::Auto => Platform::current().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "SideFX has no builds for this OS ({} {}), pass --platform or --platform-raw",
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                )
+            })?,
             PlatformArg::Linux => Platform::Linux,
             PlatformArg::Win64 => Platform::Win64,
             PlatformArg::Macos => Platform::Macos,
             PlatformArg::MacosxArm64 => Platform::MacosxArm64,
-        }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_config_leaves_the_subcommand_alone() {
+        let args = Args::try_parse_from(["houdl", "--dump-config", "list"]).unwrap();
+        assert!(matches!(args.dump_config, Some(ConfigFormat::Text)));
+        assert!(matches!(args.commands, Commands::List { .. }));
+        let args = Args::try_parse_from(["houdl", "--dump-config=json", "list"]).unwrap();
+        assert!(matches!(args.dump_config, Some(ConfigFormat::Json)));
+    }
+
+    #[test]
+    fn free_space_may_be_zero_but_speed_not() {
+        assert_eq!(parse_byte_size("0"), Ok(0));
+        assert_eq!(parse_byte_size("10G"), Ok(10 << 30));
+        assert_eq!(parse_byte_size("1.5K"), Ok(1536));
+        assert!(parse_byte_size("-1M").is_err());
+        assert_eq!(parse_speed("500K"), Ok(500 << 10));
+        assert!(parse_speed("0").is_err());
+        assert!(parse_speed("0.0001").is_err());
     }
 }
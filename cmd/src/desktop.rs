@@ -0,0 +1,55 @@
+//! `--notify` and `--reveal`, through the desktop's own command line tools: `notify-send` and
+//! `xdg-open` on Linux, `osascript` and `open` on macOS, `explorer` on Windows. Nothing is linked
+//! in, so headless builds carry no GUI dependencies and the flags just warn without a desktop.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Show a desktop notification, falling back to a warning where that isn't supported.
+pub fn notify(summary: &str, body: &str) {
+    let status = if cfg!(target_os = "linux") {
+        Command::new("notify-send").args([summary, body]).status()
+    } else if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {:?} with title {:?}",
+            body.replace('"', "'"),
+            summary.replace('"', "'")
+        );
+        Command::new("osascript").args(["-e", &script]).status()
+    } else {
+        eprintln!("[warning]: --notify is not supported on this platform");
+        return;
+    };
+    if !status.is_ok_and(|s| s.success()) {
+        eprintln!("[warning]: Could not show a desktop notification");
+    }
+}
+
+/// Open the folder containing `file` in the OS file manager, selecting the file where possible.
+pub fn reveal(file: &Path) {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(file);
+        command
+    } else if cfg!(windows) {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", file.display()));
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("xdg-open");
+        command.arg(file.parent().unwrap_or(Path::new(".")));
+        command
+    } else {
+        eprintln!("[warning]: --reveal is not supported on this platform");
+        return;
+    };
+    // The file manager outlives us, don't wait for it
+    if command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .is_err()
+    {
+        eprintln!("[warning]: Could not open the file manager");
+    }
+}
@@ -0,0 +1,77 @@
+use crate::args::OutputFormat;
+use crate::list::csv_field;
+use anyhow::Result;
+use houdini_downloader_api::Build;
+use owo_colors::OwoColorize;
+use std::io::Write;
+
+/// The build fields compared by `diff`, by name.
+fn fields(build: &Build) -> [(&'static str, &str); 4] {
+    [
+        ("date", &build.date),
+        ("platform", &build.platform),
+        ("status", &build.status),
+        ("release", &build.release),
+    ]
+}
+
+/// Print the fields of two builds side by side, differing ones highlighted when `color` is set.
+pub fn print_diff(from: &Build, to: &Build, format: OutputFormat, color: bool) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    let rows: Vec<_> = fields(from)
+        .into_iter()
+        .zip(fields(to))
+        .map(|((name, from), (_, to))| (name, from, to))
+        .collect();
+    match format {
+        OutputFormat::Text => {
+            let from_version = from.full_version();
+            let width = rows
+                .iter()
+                .map(|(_, from, _)| from.chars().count())
+                .chain([from_version.chars().count()])
+                .max()
+                .unwrap_or(0);
+            let header = format!("{:<8}  {from_version:<width$}  {}", "", to.full_version());
+            if color {
+                writeln!(stdout, "{}", header.trim_end().bold())?;
+            } else {
+                writeln!(stdout, "{}", header.trim_end())?;
+            }
+            for (name, from, to) in rows {
+                let line = format!("{name:<8}  {from:<width$}  {to}");
+                if color && from != to {
+                    writeln!(stdout, "{}", line.trim_end().yellow())?;
+                } else {
+                    writeln!(stdout, "{}", line.trim_end())?;
+                }
+            }
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => {
+            let differences: Vec<_> = rows
+                .iter()
+                .filter(|(_, from, to)| from != to)
+                .map(|(name, _, _)| *name)
+                .collect();
+            let diff = serde_json::json!({
+                "from": from,
+                "to": to,
+                "differences": differences,
+            });
+            if format == OutputFormat::Json {
+                serde_json::to_writer_pretty(&mut stdout, &diff)?;
+            } else {
+                serde_json::to_writer(&mut stdout, &diff)?;
+            }
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(stdout, "field,from,to,differs")?;
+            for (name, from, to) in rows {
+                let (csv_from, csv_to) = (csv_field(from), csv_field(to));
+                writeln!(stdout, "{name},{csv_from},{csv_to},{}", from != to)?;
+            }
+        }
+    }
+    Ok(())
+}
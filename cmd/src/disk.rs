@@ -0,0 +1,45 @@
+use std::io;
+use std::path::Path;
+
+/// Free space in bytes available to the current user on the filesystem containing `path`.
+/// Returns `None` when it can't be determined.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid NUL-terminated string and `stat` is only read on success.
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Whether the error means the filesystem is full.
+pub fn is_out_of_space(error: &io::Error) -> bool {
+    const ENOSPC: i32 = 28;
+    const ERROR_HANDLE_DISK_FULL: i32 = 39;
+    const ERROR_DISK_FULL: i32 = 112;
+
+    if matches!(
+        error.kind(),
+        io::ErrorKind::StorageFull | io::ErrorKind::WriteZero
+    ) {
+        return true;
+    }
+    match error.raw_os_error() {
+        Some(ENOSPC) if cfg!(unix) => true,
+        Some(ERROR_HANDLE_DISK_FULL | ERROR_DISK_FULL) if cfg!(windows) => true,
+        _ => false,
+    }
+}
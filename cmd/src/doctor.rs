@@ -0,0 +1,114 @@
+use anyhow::{bail, Context, Result};
+use houdini_downloader_api::{cache_dir, ClientConfig, Platform, Product, SesiClient};
+use owo_colors::OwoColorize;
+use std::net::ToSocketAddrs;
+
+/// Run the checks one after the other, stopping where a later check can't work anymore,
+/// and print a line per check so it's obvious which step fails.
+pub async fn run(
+    config: ClientConfig,
+    user_id: Option<&str>,
+    user_secret: Option<&str>,
+    product: Product,
+    platform: Result<Platform>,
+) -> Result<()> {
+    let mut failed = 0;
+    let mut report = |name: &str, result: Result<String>| match result {
+        Ok(detail) => {
+            println!("{} {name}: {detail}", "✔".green());
+            true
+        }
+        Err(e) => {
+            println!("{}", format!("✘ {name}: {e:#}").red());
+            failed += 1;
+            false
+        }
+    };
+
+    report("Cache directory", check_cache_dir(&config));
+    let reachable = report("Connection to SideFX", check_connection(&config).await);
+    let credentials = match (user_id, user_secret) {
+        (Some(id), Some(secret)) => Ok((id, secret)),
+        _ => Err(anyhow::anyhow!(
+            "SESI_USER_ID and SESI_USER_SECRET are required, or a sidefx.com entry in ~/.netrc"
+        )),
+    };
+    let client = match credentials {
+        Ok((id, secret)) if reachable => {
+            // A cached token would hide credentials that don't work anymore
+            let config = ClientConfig {
+                token_cache: false,
+                ..config
+            };
+            match SesiClient::with_config(id, secret, config).await {
+                Ok(client) => {
+                    report("Authorization", Ok("got an access token".to_string()));
+                    Some(client)
+                }
+                Err(e) => {
+                    report("Authorization", Err(e.into()));
+                    None
+                }
+            }
+        }
+        Ok(_) => {
+            println!("- Authorization: skipped, SideFX is not reachable");
+            None
+        }
+        Err(e) => {
+            report("Credentials", Err(e));
+            None
+        }
+    };
+    if let Some(client) = client {
+        let listed = async {
+            let platform = platform?;
+            let builds = client
+                .list_builds(product, platform.clone(), None::<String>, true)
+                .await
+                .context("Could not list builds")?;
+            Ok(format!("{} {product} builds for {platform}", builds.len()))
+        }
+        .await;
+        report("Build listing", listed);
+    }
+
+    if failed > 0 {
+        bail!("{failed} check(s) failed");
+    }
+    Ok(())
+}
+
+/// The cache directory can be created and written to.
+fn check_cache_dir(config: &ClientConfig) -> Result<String> {
+    let Some(dir) = config.cache_dir.clone().or_else(cache_dir) else {
+        bail!("no cache directory on this system, pass --cache-dir");
+    };
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("could not create {}", dir.to_string_lossy()))?;
+    let probe = dir.join(format!("doctor{}.tmp", std::process::id()));
+    std::fs::write(&probe, b"ok")
+        .with_context(|| format!("could not write to {}", dir.to_string_lossy()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(format!("{} is writable", dir.to_string_lossy()))
+}
+
+/// The token endpoint's host resolves and answers over HTTPS.
+async fn check_connection(config: &ClientConfig) -> Result<String> {
+    let url = reqwest::Url::parse(&config.token_url).context("invalid token URL")?;
+    let host = url.host_str().context("token URL has no host")?.to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addr = (host.as_str(), port)
+        .to_socket_addrs()
+        .with_context(|| format!("could not resolve {host}"))?
+        .next()
+        .with_context(|| format!("{host} has no address"))?;
+    let status = config
+        .http_client()?
+        .head(url.origin().ascii_serialization())
+        .send()
+        .await
+        .with_context(|| format!("could not connect to {host} ({})", addr.ip()))?
+        .status();
+    Ok(format!("{host} ({}) answered {status}", addr.ip()))
+}
@@ -0,0 +1,795 @@
+use crate::args::{DownloadArgs, ExternalDownloader};
+use crate::disk;
+use crate::download_log;
+use crate::pause;
+use crate::progress_fd::ProgressEvents;
+use anyhow::{bail, Context, Result};
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use houdini_downloader_api::download::{
+    download_build_to, finish_partial, hash_file, partial_path, remote_size, sanitize_filename,
+    verify_file, DownloadOutcome, ProgressFn, TransferOptions,
+};
+use houdini_downloader_api::{
+    format_bytes, ApiError, Build, BuildUrl, HashAlgo, Kind, Platform, Product,
+};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use owo_colors::OwoColorize;
+use std::error::Error;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A bar for the bytes of all builds of a batch, drawn above the bar of the current download.
+#[derive(Debug, Clone)]
+pub struct BatchProgress {
+    multi: MultiProgress,
+    pub total: ProgressBar,
+}
+
+impl BatchProgress {
+    pub fn new(total_bytes: u64) -> BatchProgress {
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stderr());
+        let total = multi.add(ProgressBar::new(total_bytes));
+        total.set_style(bar_style(
+            "{msg} [{wide_bar:.green/blue}] {bytes}/{total_bytes} {percent}% \
+            ({binary_bytes_per_sec}, {eta})",
+        ));
+        total.set_message("Total");
+        BatchProgress { multi, total }
+    }
+}
+
+/// The build a [`BuildUrl`] was resolved for.
+pub struct BuildId {
+    pub product: Product,
+    pub platform: Platform,
+    pub version: String,
+    pub build: u64,
+}
+
+impl BuildId {
+    /// Read the build fields `get --dry-run` adds next to the URL.
+    pub fn from_json(json: &serde_json::Value) -> Option<BuildId> {
+        Some(BuildId {
+            product: serde_json::from_value(json.get("product")?.clone()).ok()?,
+            platform: serde_json::from_value(json.get("platform")?.clone()).ok()?,
+            version: json.get("version")?.as_str()?.to_string(),
+            build: json.get("build")?.as_u64()?,
+        })
+    }
+}
+
+impl std::fmt::Display for BuildId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}.{}",
+            self.product, self.platform, self.version, self.build
+        )
+    }
+}
+
+/// How builds are downloaded, gathered from [`DownloadArgs`] so the download
+/// functions don't need a parameter per flag.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    pub output_dir: PathBuf,
+    /// Download without asking for confirmation.
+    pub auto_confirm: bool,
+    pub no_progress: bool,
+    /// Print only the [`summary_line`] of each download, on stdout.
+    pub summary_only: bool,
+    /// File descriptor for JSON progress events.
+    pub progress_fd: Option<i32>,
+    pub overwrite: bool,
+    /// Insert the platform into the saved filename.
+    pub append_platform: bool,
+    pub filename_prefix: Option<String>,
+    pub filename_suffix: Option<String>,
+    pub print_checksum: Option<HashAlgo>,
+    /// Keep an existing file that verifies instead of downloading it again.
+    pub prefer_cached: bool,
+    pub buffer_size: usize,
+    pub connections: u16,
+    pub external: Option<ExternalDownloader>,
+    pub retries: u32,
+    /// Continue the partial file of an earlier run.
+    pub keep_partial: bool,
+    /// Only check the size of the download, not its checksum.
+    pub no_verify: bool,
+    /// Disk space to leave free in `output_dir`.
+    pub min_free_space: Option<u64>,
+    /// Bytes per second.
+    pub max_speed: Option<u64>,
+    /// NDJSON file to append a record per download to.
+    pub log_json: Option<PathBuf>,
+    /// Overall progress of the batch this download is part of.
+    pub batch: Option<BatchProgress>,
+    /// Unpack a downloaded launcher ISO.
+    #[cfg(feature = "iso")]
+    pub extract_iso: bool,
+    pub notify: bool,
+    pub reveal: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            output_dir: PathBuf::from("."),
+            auto_confirm: false,
+            no_progress: false,
+            summary_only: false,
+            progress_fd: None,
+            overwrite: false,
+            append_platform: false,
+            filename_prefix: None,
+            filename_suffix: None,
+            print_checksum: None,
+            prefer_cached: false,
+            buffer_size: 1024 * 1024,
+            connections: 1,
+            external: None,
+            retries: 3,
+            keep_partial: false,
+            no_verify: false,
+            min_free_space: None,
+            max_speed: None,
+            log_json: None,
+            batch: None,
+            #[cfg(feature = "iso")]
+            extract_iso: false,
+            notify: false,
+            reveal: false,
+        }
+    }
+}
+
+impl From<&DownloadArgs> for DownloadOptions {
+    fn from(args: &DownloadArgs) -> Self {
+        DownloadOptions {
+            output_dir: args.output_dir.clone(),
+            auto_confirm: args.silent || args.auto_confirm || args.summary_only,
+            no_progress: args.silent || args.no_progress || args.summary_only,
+            summary_only: args.summary_only,
+            progress_fd: args.progress_fd,
+            overwrite: args.overwrite,
+            append_platform: args.append_platform_to_filename,
+            filename_prefix: args.filename_prefix.clone(),
+            filename_suffix: args.filename_suffix.clone(),
+            print_checksum: args.print_checksum.map(|arg| arg.algo()),
+            prefer_cached: args.prefer_cached,
+            buffer_size: args.buffer_size,
+            connections: args.connections,
+            external: args.external,
+            retries: args.retry_download,
+            keep_partial: args.keep_partial,
+            no_verify: args.no_verify,
+            min_free_space: args.min_free_space,
+            max_speed: args.max_speed,
+            log_json: args.log_json.clone(),
+            batch: None,
+            #[cfg(feature = "iso")]
+            extract_iso: args.extract_iso,
+            notify: args.notify,
+            reveal: args.reveal,
+        }
+    }
+}
+
+impl DownloadOptions {
+    /// Tell what's happening on stderr, unless only the summary line is wanted.
+    pub fn status(&self, message: impl std::fmt::Display) {
+        if !self.summary_only {
+            eprintln!("{message}");
+        }
+    }
+}
+
+/// The name `build_info` is saved under: the platform of `id` inserted before the extension if
+/// [`DownloadOptions::append_platform`] is set, then the suffix before the extension, then the
+/// prefix in front, e.g. `prod_houdini-20.0.547-linux_2024-05.tar.gz`.
+pub fn saved_filename(
+    build_info: &BuildUrl,
+    id: Option<&BuildId>,
+    opts: &DownloadOptions,
+) -> String {
+    let filename = match id {
+        Some(id) if opts.append_platform => with_platform(&build_info.filename, &id.platform),
+        _ => build_info.filename.clone(),
+    };
+    let filename = match &opts.filename_suffix {
+        Some(suffix) => {
+            let (stem, ext) = split_extension(&filename);
+            format!("{stem}{suffix}{ext}")
+        }
+        None => filename,
+    };
+    match &opts.filename_prefix {
+        Some(prefix) => format!("{prefix}{filename}"),
+        None => filename,
+    }
+}
+
+/// Whether `filename`, as named by [`saved_filename`], is in the output directory.
+pub fn is_saved(opts: &DownloadOptions, filename: &str) -> bool {
+    sanitize_filename(filename).is_ok_and(|name| opts.output_dir.join(name).exists())
+}
+
+/// `houdini-20.0.547.tar.gz` becomes `houdini-20.0.547-linux.tar.gz`. Names that already end
+/// in the platform are kept.
+fn with_platform(filename: &str, platform: &Platform) -> String {
+    let platform = platform.as_api_str();
+    let (stem, ext) = split_extension(filename);
+    if stem.ends_with(&format!("-{platform}")) {
+        return filename.to_string();
+    }
+    format!("{stem}-{platform}{ext}")
+}
+
+/// `houdini-20.0.547.tar.gz` is split into `houdini-20.0.547` and `.tar.gz`.
+fn split_extension(filename: &str) -> (&str, &str) {
+    let split = [".tar.gz", ".tar.xz", ".tar.bz2"]
+        .iter()
+        .find_map(|ext| filename.strip_suffix(ext).map(|stem| stem.len()))
+        .or_else(|| filename.rfind('.').filter(|&i| i > 0))
+        .unwrap_or(filename.len());
+    filename.split_at(split)
+}
+
+/// How [`download_build`] ended.
+enum Done {
+    Downloaded(DownloadOutcome),
+    /// The file was already in the output directory and kept, with its hash if it was verified.
+    Existing {
+        path: PathBuf,
+        hash: Option<String>,
+    },
+    Declined,
+}
+
+/// `id` is used for the `--log-json` record and `--append-platform-to-filename`.
+///
+/// An expired URL gets no `--summary-only` line, the caller resolves a fresh one and tries
+/// again or reports it with [`print_failure`].
+pub async fn download_build(
+    http: &reqwest::Client,
+    build_info: BuildUrl,
+    id: Option<&BuildId>,
+    opts: &DownloadOptions,
+    confirm: bool,
+) -> Result<()> {
+    let filename = saved_filename(&build_info, id, opts);
+    let started = Instant::now();
+    #[cfg(feature = "iso")]
+    let iso = opts.extract_iso.then(|| build_info.clone());
+    let result = download(http, build_info, id, opts, confirm).await;
+    #[cfg(feature = "iso")]
+    let result = match (result, iso) {
+        (Ok(done), Some(build_info)) => extract_iso(&done, &build_info, id, opts)
+            .await
+            .map(|()| done),
+        (result, _) => result,
+    };
+    let expired = result.as_ref().is_err_and(is_url_expired);
+    if opts.summary_only && !expired {
+        println!("{}", summary_line(&filename, &result, started.elapsed()));
+    }
+    result.map(|_| ())
+}
+
+/// `--extract-iso`: unpack the launcher ISO of `done` once its checksum is verified. The image
+/// is kept, also when it can't be extracted.
+#[cfg(feature = "iso")]
+async fn extract_iso(
+    done: &Done,
+    build_info: &BuildUrl,
+    id: Option<&BuildId>,
+    opts: &DownloadOptions,
+) -> Result<()> {
+    let path = match done {
+        Done::Downloaded(outcome) => &outcome.path,
+        Done::Existing { path, .. } => path,
+        Done::Declined => return Ok(()),
+    };
+    let is_iso = match id {
+        Some(id) => id.product == Product::LauncherIso,
+        None => path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("iso")),
+    };
+    if !is_iso {
+        eprintln!(
+            "[warning]: {} isn't a launcher ISO, not extracting it",
+            path.to_string_lossy()
+        );
+        return Ok(());
+    }
+    let verified = match done {
+        Done::Downloaded(outcome) => outcome.hash_matched,
+        Done::Existing { hash: Some(_), .. } => true,
+        _ => {
+            opts.status(format!("Verifying {}", path.to_string_lossy()));
+            verify_file(path, build_info)
+                .await
+                .context("Could not verify the ISO")?
+        }
+    };
+    if !verified {
+        bail!(
+            "Not extracting {}, its checksum couldn't be verified",
+            path.to_string_lossy()
+        );
+    }
+    let dir = path.with_extension("");
+    opts.status(format!(
+        "Extracting {} to {}",
+        path.to_string_lossy(),
+        dir.to_string_lossy()
+    ));
+    let (iso, target) = (path.clone(), dir.clone());
+    let files = tokio::task::spawn_blocking(move || crate::iso::extract(&iso, &target))
+        .await?
+        .with_context(|| {
+            format!(
+                "Could not extract {}, the ISO is kept in place",
+                path.to_string_lossy()
+            )
+        })?;
+    opts.status(format!(
+        "{} Extracted {files} files to {}",
+        "✔".green(),
+        dir.to_string_lossy()
+    ));
+    Ok(())
+}
+
+/// The `--summary-only` line of a download that failed outside of [`download_build`].
+pub fn print_failure(opts: &DownloadOptions, filename: &str, e: &anyhow::Error) {
+    if opts.summary_only {
+        println!("FAIL {filename} {e:#}");
+    }
+}
+
+/// `--summary-only`: `OK <filename> <size> <algo>=<hash> <seconds>s`, where the hash is
+/// `unverified` if it wasn't computed, `SKIP <filename> declined` or
+/// `FAIL <filename> <seconds>s <error>`, like [`print_failure`] but with the time. Sizes have no space, to keep the fields apart.
+fn summary_line(filename: &str, result: &Result<Done>, elapsed: Duration) -> String {
+    let elapsed = format!("{:.1}s", elapsed.as_secs_f64());
+    let size = |bytes| format_bytes(bytes).replace(' ', "");
+    let hash = |hash: Option<&str>| match hash {
+        Some(hash) => {
+            let algo = HashAlgo::from_hex_len(hash).map_or("checksum".into(), |a| a.to_string());
+            format!("{algo}={hash}")
+        }
+        None => "unverified".to_string(),
+    };
+    match result {
+        Ok(Done::Downloaded(outcome)) => format!(
+            "OK {filename} {} {} {elapsed}",
+            size(outcome.bytes_written),
+            hash(outcome.computed_hash.as_deref())
+        ),
+        Ok(Done::Existing {
+            path,
+            hash: verified,
+        }) => {
+            let bytes = std::fs::metadata(path).map_or(0, |meta| meta.len());
+            format!(
+                "OK {filename} {} {} {elapsed} existing",
+                size(bytes),
+                hash(verified.as_deref())
+            )
+        }
+        Ok(Done::Declined) => format!("SKIP {filename} declined"),
+        Err(e) => format!("FAIL {filename} {elapsed} {e:#}"),
+    }
+}
+
+async fn download(
+    http: &reqwest::Client,
+    build_info: BuildUrl,
+    id: Option<&BuildId>,
+    opts: &DownloadOptions,
+    confirm: bool,
+) -> Result<Done> {
+    let output_dir = &opts.output_dir;
+    if opts.append_platform && id.is_none() {
+        eprintln!("[warning]: The platform of the build is unknown, keeping the filename");
+    }
+    let filename = &sanitize_filename(&saved_filename(&build_info, id, opts))?;
+    if filename != &build_info.filename {
+        opts.status(format!("Saving {:?} as {filename:?}", build_info.filename));
+    }
+    let mut build_info = BuildUrl {
+        filename: filename.clone(),
+        ..build_info
+    };
+    if build_info.size == 0 {
+        match remote_size(http, &build_info).await {
+            Some(size) => build_info.size = size,
+            None => eprintln!("[warning]: The size of {filename} is unknown"),
+        }
+    }
+    let output = output_dir.join(filename);
+    if opts.prefer_cached && output.exists() {
+        let size = std::fs::metadata(&output)
+            .context("Could not verify the existing file")?
+            .len();
+        // A size that differs already proves the file is wrong, without reading gigabytes
+        if build_info.size > 0 && size != build_info.size {
+            opts.status(format!(
+                "Existing file is {} but the build is {}, downloading it again",
+                format_bytes(size),
+                format_bytes(build_info.size)
+            ));
+        } else {
+            opts.status(format!("Verifying existing {}", output.to_string_lossy()));
+            let verified = verify_file(&output, &build_info)
+                .await
+                .context("Could not verify the existing file")?;
+            if verified {
+                opts.status(format!(
+                    "{} Reused existing verified file: {}",
+                    "✔".green(),
+                    output.to_string_lossy()
+                ));
+                print_checksum_line(opts, &output, Some(&build_info.hash)).await?;
+                return Ok(Done::Existing {
+                    path: output,
+                    hash: Some(build_info.hash.to_lowercase()),
+                });
+            }
+            opts.status("Existing file doesn't match the build, downloading it again");
+        }
+    } else if !opts.overwrite && output.exists() {
+        opts.status(format!(
+            "File already downloaded: {}",
+            output.to_string_lossy()
+        ));
+        print_checksum_line(opts, &output, None).await?;
+        return Ok(Done::Existing {
+            path: output,
+            hash: None,
+        });
+    }
+    let algo = HashAlgo::from_hex_len(&build_info.hash);
+    if confirm {
+        let hash = match algo {
+            Some(algo) => format!("{algo} {}", build_info.hash),
+            None => format!("checksum {:?}", build_info.hash),
+        };
+        let size = match build_info.size {
+            0 => "unknown size".to_string(),
+            size => format_bytes(size),
+        };
+        let confirmation = ask(Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Download {filename} ({size}, {hash})?")))?;
+        match confirmation {
+            None => return Ok(Done::Declined),
+            Some(inp) if !inp => return Ok(Done::Declined),
+            _ => {}
+        }
+    }
+    if let Some(available) = disk::available_space(output_dir) {
+        let margin = opts.min_free_space.unwrap_or(0);
+        if available < build_info.size.saturating_add(margin) {
+            bail!(
+                "Not enough disk space in {}: {} required, {} available, {} kept free",
+                output_dir.to_string_lossy(),
+                format_bytes(build_info.size),
+                format_bytes(available),
+                format_bytes(margin)
+            );
+        }
+    }
+    match algo {
+        _ if opts.no_verify => eprintln!("[warning]: Skipping checksum verification (--no-verify)"),
+        None => eprintln!(
+            "[warning]: Unrecognized build checksum {:?}, skipping verification",
+            build_info.hash
+        ),
+        Some(algo) if !algo.is_supported() => {
+            eprintln!("[warning]: {algo} checksums are not supported, skipping verification")
+        }
+        Some(_) => {}
+    }
+    if let Some(external) = opts.external {
+        return download_external(external, &build_info, id, opts, algo)
+            .await
+            .map(Done::Downloaded);
+    }
+    let downloading_started_msg = format!("Downloading {}", filename);
+    let bar = if !opts.no_progress {
+        // Without a size there is nothing to fill a bar or estimate the time left with
+        let (len, template) = match build_info.size {
+            0 => (
+                None,
+                "{msg}\n{spinner:.green} [{elapsed_precise}] {bytes} ({binary_bytes_per_sec})",
+            ),
+            size => (
+                Some(size),
+                "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] \
+                {bytes}/{total_bytes} ({binary_bytes_per_sec}, {eta})",
+            ),
+        };
+        let bar = match &opts.batch {
+            Some(batch) => batch.multi.add(ProgressBar::with_draw_target(
+                len,
+                ProgressDrawTarget::hidden(),
+            )),
+            None => ProgressBar::with_draw_target(len, ProgressDrawTarget::stderr()),
+        };
+        bar.set_style(bar_style(template));
+        bar.set_message(downloading_started_msg.clone());
+        Some(bar)
+    } else {
+        opts.status(&downloading_started_msg);
+        None
+    };
+    let events = opts
+        .progress_fd
+        .map(|fd| ProgressEvents::new(fd, filename, build_info.size))
+        .transpose()?;
+    let written = Arc::new(AtomicU64::new(0));
+    let on_progress = {
+        let (bar, written) = (bar.clone(), written.clone());
+        let total = bar
+            .as_ref()
+            .and(opts.batch.as_ref())
+            .map(|batch| batch.total.clone());
+        move |bytes| {
+            written.fetch_add(bytes, Ordering::Relaxed);
+            // Updated on every chunk: a million `inc` calls take about 42 ms, and batching them
+            // saves nothing because indicatif already limits redraws to 20 a second
+            if let Some(bar) = &bar {
+                bar.inc(bytes);
+            }
+            if let Some(total) = &total {
+                total.inc(bytes);
+            }
+            if let Some(events) = &events {
+                events.inc(bytes);
+            }
+        }
+    };
+    let transfer = TransferOptions {
+        buffer_size: opts.buffer_size,
+        max_speed: opts.max_speed,
+        connections: opts.connections,
+        retries: opts.retries,
+        persist_progress: opts.keep_partial,
+        verify_hash: !opts.no_verify,
+        paused: pause::flag(),
+        on_progress: Some(Arc::new(on_progress) as ProgressFn),
+    };
+    let paused = transfer.paused.clone().map(|paused| {
+        let (bar, message) = (bar.clone(), downloading_started_msg.clone());
+        tokio::spawn(pause::report(paused, bar, message))
+    });
+    let started = Instant::now();
+    let result = download_build_to(http, &build_info, output_dir, &transfer).await;
+    if let Some(paused) = paused {
+        paused.abort();
+    }
+    if let Some(log) = &opts.log_json {
+        let written = written.load(Ordering::Relaxed);
+        let result = result.as_ref().map_err(|e| e as _);
+        download_log::append(log, id, &build_info, started, written, result);
+    }
+    let outcome = result
+        .map_err(|e| download_error(e, &output, build_info.size))
+        .inspect_err(|_| report_kept(&output))?;
+    let outcome = check_outcome(outcome, algo, bar, opts)?;
+    print_checksum_line(opts, &outcome.path, outcome.computed_hash.as_deref()).await?;
+    if opts.notify {
+        crate::desktop::notify("Houdini download finished", filename);
+    }
+    if opts.reveal {
+        crate::desktop::reveal(&outcome.path);
+    }
+    Ok(Done::Downloaded(outcome))
+}
+
+/// Let an external program write the partial file, then verify it like an in-process download.
+async fn download_external(
+    external: ExternalDownloader,
+    build_info: &BuildUrl,
+    id: Option<&BuildId>,
+    opts: &DownloadOptions,
+    algo: Option<HashAlgo>,
+) -> Result<DownloadOutcome> {
+    let (partial, output) = partial_path(build_info, &opts.output_dir)?;
+    let mut command = external.command(&build_info.download_url, &partial, opts.no_progress);
+    let program = command.get_program().to_owned();
+    let started = Instant::now();
+    // Waiting for the process blocks, keep it off the runtime's only thread
+    let status = tokio::task::spawn_blocking(move || command.status())
+        .await?
+        .with_context(|| format!("Could not run {program:?}"))?;
+    let written = tokio::fs::metadata(&partial)
+        .await
+        .map_or(0, |meta| meta.len());
+    if !status.success() {
+        let _ = tokio::fs::remove_file(&partial).await;
+        let message = format!("{program:?} failed: {status}");
+        if let Some(log) = &opts.log_json {
+            let e = std::io::Error::other(message.clone());
+            download_log::append(log, id, build_info, started, written, Err(&e));
+        }
+        report_kept(&output);
+        bail!(message);
+    }
+    let result = finish_partial(build_info, &opts.output_dir).await;
+    if let Some(log) = &opts.log_json {
+        let result = result.as_ref().map_err(|e| e as _);
+        download_log::append(log, id, build_info, started, written, result);
+    }
+    let outcome = result
+        .map_err(|e| download_error(e, &output, build_info.size))
+        .inspect_err(|_| report_kept(&output))?;
+    let outcome = check_outcome(outcome, algo, None, opts)?;
+    print_checksum_line(opts, &outcome.path, outcome.computed_hash.as_deref()).await?;
+    Ok(outcome)
+}
+
+/// Show `prompt`, `None` if it was cancelled. Without a terminal to answer on, e.g. in CI or
+/// with piped input, it fails with a hint instead of waiting or a terse error from dialoguer.
+pub fn ask(prompt: Confirm) -> Result<Option<bool>> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        bail!(
+            "Refusing to prompt without a terminal, pass --auto-confirm or --silent to download \
+            without confirmation"
+        );
+    }
+    Ok(prompt.interact_opt()?)
+}
+
+/// The new file only replaces an existing one once it's verified, say so when that didn't happen.
+fn report_kept(output: &Path) {
+    if output.exists() {
+        eprintln!(
+            "Overwrite aborted, the existing {} was kept",
+            output.to_string_lossy()
+        );
+    }
+}
+
+/// Print how the download compares to the build and fail if it was discarded.
+fn check_outcome(
+    outcome: DownloadOutcome,
+    algo: Option<HashAlgo>,
+    bar: Option<ProgressBar>,
+    opts: &DownloadOptions,
+) -> Result<DownloadOutcome> {
+    if outcome.is_accepted() {
+        let message = format!("Downloaded: {}", outcome.path.to_string_lossy());
+        match bar {
+            Some(bar) => bar.finish_with_message(message),
+            None => opts.status(message),
+        }
+    } else if let Some(bar) = bar {
+        bar.abandon();
+    }
+    if let Some(computed) = outcome
+        .computed_hash
+        .as_ref()
+        .filter(|_| !opts.summary_only)
+    {
+        print_checksum(algo, &outcome.expected_hash, computed);
+    }
+    if outcome.is_accepted() {
+        return Ok(outcome);
+    }
+    report_kept(&outcome.path);
+    if !outcome.size_matched {
+        bail!(
+            "Downloaded {} bytes instead of {}, the download was discarded",
+            outcome.bytes_written,
+            outcome.expected_size
+        );
+    }
+    bail!("Downloaded file hash is different from the build hash, the download was discarded");
+}
+
+/// `--print-checksum`: the saved file in the format of coreutils' `md5sum`. A `known` digest of
+/// the file with the requested algorithm is used as is, otherwise the file is read again.
+async fn print_checksum_line(
+    opts: &DownloadOptions,
+    output: &Path,
+    known: Option<&str>,
+) -> Result<()> {
+    let Some(algo) = opts.print_checksum else {
+        return Ok(());
+    };
+    let hash = match known.filter(|hash| HashAlgo::from_hex_len(hash) == Some(algo)) {
+        Some(hash) => hash.to_lowercase(),
+        None => hash_file(output, algo)
+            .await
+            .context("Could not compute the checksum")?,
+    };
+    println!("{hash}  {}", output.to_string_lossy());
+    Ok(())
+}
+
+/// PASS/FAIL line with both hashes in full, so they can be checked against the SideFX website.
+fn print_checksum(algo: Option<HashAlgo>, expected: &str, computed: &str) {
+    let algo = algo.map_or("checksum".to_string(), |algo| algo.to_string());
+    if computed.eq_ignore_ascii_case(expected) {
+        eprintln!("{} {algo} checksum PASS", "✔".green());
+    } else {
+        eprintln!("{}", format!("✘ {algo} checksum FAIL").red());
+    }
+    eprintln!("  expected: {expected}");
+    eprintln!("  computed: {computed}");
+}
+
+/// The listed build info, if any, merged with the download URL fields for `--write-metadata`.
+pub fn metadata(id: &BuildId, listed: Option<&Build>, build_info: &BuildUrl) -> serde_json::Value {
+    let mut metadata = serde_json::json!({
+        "product": id.product,
+        "platform": id.platform,
+        "version": id.version,
+        "build": id.build,
+    });
+    for value in [
+        serde_json::to_value(listed),
+        serde_json::to_value(build_info),
+    ] {
+        if let Ok(serde_json::Value::Object(fields)) = value {
+            metadata.as_object_mut().expect("object").extend(fields);
+        }
+    }
+    metadata
+}
+
+/// Write `metadata` to `<filename>.json` next to the installer.
+pub fn write_metadata(
+    opts: &DownloadOptions,
+    filename: &str,
+    metadata: &serde_json::Value,
+) -> Result<()> {
+    let path = opts
+        .output_dir
+        .join(format!("{}.json", sanitize_filename(filename)?));
+    let data = serde_json::to_vec_pretty(metadata)?;
+    std::fs::write(&path, data)
+        .with_context(|| format!("Could not write {}", path.to_string_lossy()))?;
+    opts.status(format!("Metadata: {}", path.to_string_lossy()));
+    Ok(())
+}
+
+/// Progress bar style with `template`. A template indicatif rejects falls back to its plain
+/// default bar with a warning, a cosmetic mistake shouldn't stop the download.
+fn bar_style(template: &str) -> ProgressStyle {
+    match ProgressStyle::default_bar().template(template) {
+        Ok(style) => style.progress_chars("#>-"),
+        Err(e) => {
+            eprintln!("[warning]: Invalid progress bar template, using the plain bar: {e}");
+            ProgressStyle::default_bar()
+        }
+    }
+}
+
+/// Whether the download failed because the URL expired, see [`Kind::UrlExpired`].
+pub fn is_url_expired(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<ApiError>()
+        .is_some_and(|e| e.kind() == Kind::UrlExpired)
+}
+
+fn download_error(e: ApiError, output: &Path, size: u64) -> anyhow::Error {
+    let out_of_space = e
+        .source()
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .is_some_and(disk::is_out_of_space);
+    if out_of_space {
+        anyhow::anyhow!(
+            "Ran out of disk space while writing {} (needed {})",
+            output.to_string_lossy(),
+            format_bytes(size)
+        )
+    } else {
+        anyhow::Error::new(e).context(format!("Error downloading {}", output.to_string_lossy()))
+    }
+}
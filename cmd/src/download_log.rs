@@ -0,0 +1,74 @@
+use crate::download::BuildId;
+use houdini_downloader_api::download::DownloadOutcome;
+use houdini_downloader_api::BuildUrl;
+use owo_colors::OwoColorize;
+use serde_json::json;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Append one NDJSON record about a finished or failed download to `path`.
+/// A log that can't be written only gets a warning, the download itself succeeded or failed already.
+pub fn append(
+    path: &Path,
+    id: Option<&BuildId>,
+    build_info: &BuildUrl,
+    started: Instant,
+    bytes_written: u64,
+    result: Result<&DownloadOutcome, &(dyn Error + 'static)>,
+) {
+    let duration = started.elapsed().as_secs_f64();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let (computed_hash, hash_matched, error) = match result {
+        Ok(outcome) => (
+            outcome.computed_hash.clone(),
+            outcome
+                .computed_hash
+                .is_some()
+                .then_some(outcome.hash_matched),
+            match (outcome.size_matched, outcome.is_accepted()) {
+                (_, true) => None,
+                (false, _) => Some("size mismatch, download discarded".to_string()),
+                (true, false) => Some("hash mismatch, download discarded".to_string()),
+            },
+        ),
+        Err(e) => (None, None, Some(e.to_string())),
+    };
+    let record = json!({
+        "timestamp": timestamp,
+        "product": id.map(|id| id.product.as_api_str()),
+        "platform": id.map(|id| id.platform.as_api_str()),
+        "version": id.map(|id| id.version.as_str()),
+        "build": id.map(|id| id.build),
+        "filename": build_info.filename,
+        "size": build_info.size,
+        "bytes_written": bytes_written,
+        "duration_secs": duration,
+        "average_speed": if duration > 0.0 { bytes_written as f64 / duration } else { 0.0 },
+        "expected_hash": build_info.hash,
+        "computed_hash": computed_hash,
+        "hash_matched": hash_matched,
+        "error": error,
+    });
+    let mut line = record.to_string();
+    line.push('\n');
+    // One write per record, so concurrent runs appending to the same log don't interleave lines
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+    if let Err(e) = written {
+        eprintln!(
+            "{}",
+            format!(
+                "[warning]: Could not write to {}: {e}",
+                path.to_string_lossy()
+            )
+            .yellow()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! `--dump-config`: the settings in effect once the command line, environment and `.netrc`
+//! are merged, to find out which source won without making a request.
+
+use crate::args::{Args, ConfigFormat};
+use anyhow::Result;
+use houdini_downloader_api::{ClientConfig, Product};
+use serde_json::{json, Map, Value};
+
+/// Environment variables reqwest reads the proxy for HTTPS requests from, in order.
+const HTTPS_PROXY: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+pub fn print(
+    args: &Args,
+    product: Product,
+    config: &ClientConfig,
+    format: ConfigFormat,
+) -> Result<()> {
+    let path =
+        |path: Option<std::path::PathBuf>| path.map(|path| path.to_string_lossy().into_owned());
+    let mut settings: Vec<(&str, Value)> = vec![
+        ("product", json!(product.to_string())),
+        (
+            "platform",
+            json!(args
+                .platform()
+                .map_or_else(|e| e.to_string(), |p| p.to_string())),
+        ),
+        (
+            "user_id",
+            json!(args.credential_source(&args.user_id, "SESI_USER_ID")),
+        ),
+        (
+            "user_secret",
+            json!(args.credential_source(&args.user_secret, "SESI_USER_SECRET")),
+        ),
+        ("token_url", json!(config.token_url)),
+        ("api_url", json!(config.api_url)),
+        ("user_agent", json!(config.user_agent)),
+        // Values of extra headers are often gateway tokens
+        (
+            "headers",
+            json!(config
+                .headers
+                .keys()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()),
+        ),
+        ("proxy", json!(https_proxy())),
+        ("no_proxy", json!(env_var(&["NO_PROXY", "no_proxy"]))),
+        ("token_cache_file", json!(path(config.token_cache_file()))),
+        ("listing_cache_dir", json!(path(config.listing_cache_dir()))),
+        ("jobs", json!(config.max_concurrent_requests)),
+        ("api_retries", json!(config.api_retries)),
+        ("max_redirects", json!(config.max_redirects)),
+        (
+            "pool_max_idle_per_host",
+            json!(config.pool_max_idle_per_host),
+        ),
+        (
+            "pool_idle_timeout",
+            json!(config.pool_idle_timeout.map(|timeout| timeout.as_secs())),
+        ),
+        ("http1_only", json!(config.http1_only)),
+        ("insecure", json!(config.danger_accept_invalid_certs)),
+        (
+            "cacert",
+            json!(args
+                .cacert
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()),
+        ),
+        ("trace_http", json!(config.trace_http)),
+    ];
+    if let Some(download) = args.download() {
+        settings.extend([
+            ("output_dir", json!(download.output_dir.to_string_lossy())),
+            ("connections", json!(download.connections)),
+            ("retry_download", json!(download.retry_download)),
+            ("max_speed", json!(download.max_speed)),
+            ("min_free_space", json!(download.min_free_space)),
+        ]);
+    }
+
+    match format {
+        ConfigFormat::Json => {
+            let settings: Map<String, Value> = settings
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&settings)?);
+        }
+        ConfigFormat::Text => {
+            let width = settings.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+            for (key, value) in settings {
+                let value = match value {
+                    Value::Null => "-".to_string(),
+                    Value::String(value) => value,
+                    Value::Array(values) if values.is_empty() => "-".to_string(),
+                    Value::Array(values) => values
+                        .iter()
+                        .map(|value| {
+                            value
+                                .as_str()
+                                .map_or_else(|| value.to_string(), str::to_string)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    value => value.to_string(),
+                };
+                println!("{key:width$}  {value}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+/// The proxy SideFX is reached through, with its password replaced.
+fn https_proxy() -> Option<String> {
+    let proxy = env_var(&HTTPS_PROXY)?;
+    match reqwest::Url::parse(&proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("***"));
+            Some(url.to_string())
+        }
+        _ => Some(proxy),
+    }
+}
@@ -0,0 +1,392 @@
+//! `--extract-iso`: unpack the launcher ISO into a directory, without mounting it and so
+//! without root. Reads ISO 9660 with the Joliet extension for long names, which is what SideFX
+//! images use; Rock Ridge attributes are ignored and UDF-only images aren't supported.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const SECTOR: u64 = 2048;
+/// Volume descriptors start after the 16 sectors of the system area.
+const FIRST_DESCRIPTOR: u64 = 16;
+/// Escape sequences marking a supplementary descriptor as Joliet, for UCS-2 levels 1 to 3.
+const JOLIET: [&[u8]; 3] = [b"%/@", b"%/C", b"%/E"];
+const DIRECTORY: u8 = 0x02;
+const MULTI_EXTENT: u8 = 0x80;
+/// Largest directory read into memory. Its size comes from the image, and real directories
+/// take a few sectors, so anything near this is a corrupt image.
+const MAX_DIRECTORY: u64 = 16 << 20;
+
+/// A directory tree: the root of a volume descriptor.
+struct Volume {
+    block_size: u64,
+    root: Extent,
+    joliet: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Extent {
+    start: u64,
+    len: u64,
+}
+
+/// Extract every file of the image at `iso` into `dir`, returning the number of files.
+pub fn extract(iso: &Path, dir: &Path) -> Result<usize> {
+    let mut image =
+        File::open(iso).with_context(|| format!("Could not open {}", iso.to_string_lossy()))?;
+    let volume = read_volume(&mut image)?;
+    let root = volume.root;
+    let mut extractor = Extractor {
+        image,
+        volume,
+        files: 0,
+        visited: HashSet::new(),
+    };
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Could not create {}", dir.to_string_lossy()))?;
+    extractor.directory(root, dir)?;
+    Ok(extractor.files)
+}
+
+/// The Joliet volume if there is one, for its long names, otherwise the primary volume.
+fn read_volume(image: &mut File) -> Result<Volume> {
+    let mut primary = None;
+    let mut sector = [0; SECTOR as usize];
+    for index in FIRST_DESCRIPTOR.. {
+        image.seek(SeekFrom::Start(index * SECTOR))?;
+        if image.read_exact(&mut sector).is_err() || &sector[1..6] != b"CD001" {
+            break;
+        }
+        let joliet = match sector[0] {
+            1 => false,
+            2 if JOLIET.contains(&&sector[88..91]) => true,
+            255 => break,
+            _ => continue,
+        };
+        let block_size = u64::from(u16::from_le_bytes([sector[128], sector[129]]));
+        let Some(root) = record(&sector[156..190]) else {
+            bail!("Invalid root directory in the ISO image");
+        };
+        let volume = Volume {
+            block_size,
+            root: root.extent,
+            joliet,
+        };
+        if joliet {
+            return Ok(volume);
+        }
+        primary = Some(volume);
+    }
+    primary.context("Not an ISO 9660 image, UDF-only images aren't supported")
+}
+
+/// A file or directory entry.
+struct Record<'a> {
+    extent: Extent,
+    flags: u8,
+    name: &'a [u8],
+}
+
+/// The record at the start of `data`, `None` for the padding at the end of a sector.
+fn record(data: &[u8]) -> Option<Record<'_>> {
+    let len = usize::from(*data.first()?);
+    if len < 34 || len > data.len() {
+        return None;
+    }
+    let name_len = usize::from(data[32]);
+    Some(Record {
+        extent: Extent {
+            start: u64::from(u32::from_le_bytes(data[2..6].try_into().ok()?)),
+            len: u64::from(u32::from_le_bytes(data[10..14].try_into().ok()?)),
+        },
+        flags: data[25],
+        name: data.get(33..33 + name_len)?,
+    })
+}
+
+struct Extractor {
+    image: File,
+    volume: Volume,
+    files: usize,
+    /// Directory extents already extracted, so a corrupt image can't loop forever.
+    visited: HashSet<u64>,
+}
+
+impl Extractor {
+    fn directory(&mut self, extent: Extent, dir: &Path) -> Result<()> {
+        if !self.visited.insert(extent.start) {
+            bail!("Invalid ISO image, a directory contains itself");
+        }
+        if extent.len > MAX_DIRECTORY {
+            bail!("Invalid ISO image, a directory takes {} bytes", extent.len);
+        }
+        let mut data = vec![0; extent.len as usize];
+        self.image
+            .seek(SeekFrom::Start(extent.start * self.volume.block_size))?;
+        self.image
+            .read_exact(&mut data)
+            .context("The ISO image is truncated")?;
+        // Parts of a file larger than 4 GiB, each in its own record
+        let mut parts = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let Some(record) = record(&data[offset..]) else {
+                // Records don't cross sectors, the rest of this one is padding
+                offset = (offset / SECTOR as usize + 1) * SECTOR as usize;
+                continue;
+            };
+            offset += usize::from(data[offset]);
+            // `.` and `..`
+            if record.name == [0] || record.name == [1] {
+                continue;
+            }
+            let path = dir.join(self.name(record.name)?);
+            if record.flags & DIRECTORY != 0 {
+                std::fs::create_dir_all(&path)
+                    .with_context(|| format!("Could not create {}", path.to_string_lossy()))?;
+                self.directory(record.extent, &path)?;
+                continue;
+            }
+            parts.push(record.extent);
+            if record.flags & MULTI_EXTENT == 0 {
+                self.file(&std::mem::take(&mut parts), &path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn file(&mut self, parts: &[Extent], path: &Path) -> Result<()> {
+        let mut out = File::create(path)
+            .with_context(|| format!("Could not create {}", path.to_string_lossy()))?;
+        for part in parts {
+            self.image
+                .seek(SeekFrom::Start(part.start * self.volume.block_size))?;
+            let copied = std::io::copy(&mut (&mut self.image).take(part.len), &mut out)
+                .with_context(|| format!("Could not write {}", path.to_string_lossy()))?;
+            if copied < part.len {
+                bail!("The ISO image is truncated");
+            }
+        }
+        self.files += 1;
+        Ok(())
+    }
+
+    /// The name of a record without its `;1` version, refusing ones that would escape `dir`.
+    fn name(&self, raw: &[u8]) -> Result<String> {
+        let name = if self.volume.joliet {
+            let units: Vec<u16> = raw
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            String::from_utf8_lossy(raw).into_owned()
+        };
+        let name = name.split_once(';').map_or(name.as_str(), |(name, _)| name);
+        // Primary volume names keep the dot of an empty extension
+        let name = name.strip_suffix('.').unwrap_or(name);
+        if matches!(name, "" | "." | "..") || name.contains(['/', '\\']) {
+            bail!("Invalid file name {name:?} in the ISO image");
+        }
+        Ok(name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// An image written sector by sector, starting after the system area and the room for a
+    /// primary, a Joliet and a terminating volume descriptor.
+    struct Image(Vec<u8>);
+
+    impl Image {
+        fn new() -> Image {
+            let mut image = vec![0; (FIRST_DESCRIPTOR as usize + 3) * SECTOR as usize];
+            let terminator = (FIRST_DESCRIPTOR as usize + 2) * SECTOR as usize;
+            image[terminator] = 255;
+            image[terminator + 1..terminator + 6].copy_from_slice(b"CD001");
+            Image(image)
+        }
+
+        /// Append `data` at the next free sector, returning its number.
+        fn add(&mut self, data: &[u8]) -> u32 {
+            let start = self.0.len() / SECTOR as usize;
+            self.0.extend_from_slice(data);
+            self.0
+                .resize(self.0.len().next_multiple_of(SECTOR as usize), 0);
+            start as u32
+        }
+
+        /// The primary (`index` 0) or Joliet (1) volume descriptor, with its root directory.
+        fn volume(&mut self, index: usize, joliet: bool, root: Extent) {
+            let start = (FIRST_DESCRIPTOR as usize + index) * SECTOR as usize;
+            let sector = &mut self.0[start..start + SECTOR as usize];
+            sector[0] = if joliet { 2 } else { 1 };
+            sector[1..6].copy_from_slice(b"CD001");
+            if joliet {
+                sector[88..91].copy_from_slice(b"%/E");
+            }
+            sector[128..130].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+            sector[156..190].copy_from_slice(&entry(root, DIRECTORY, &[0]));
+        }
+
+        fn extract(&self, name: &str) -> (Result<usize>, PathBuf) {
+            let base =
+                std::env::temp_dir().join(format!("houdl-iso-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&base);
+            std::fs::create_dir_all(&base).unwrap();
+            let iso = base.join("image.iso");
+            std::fs::write(&iso, &self.0).unwrap();
+            (extract(&iso, &base.join("out")), base)
+        }
+    }
+
+    fn extent(start: u32, len: usize) -> Extent {
+        Extent {
+            start: start.into(),
+            len: len as u64,
+        }
+    }
+
+    /// A directory record.
+    fn entry(extent: Extent, flags: u8, name: &[u8]) -> Vec<u8> {
+        let len = (33 + name.len()).next_multiple_of(2);
+        let mut record = vec![0; len];
+        record[0] = len as u8;
+        record[2..6].copy_from_slice(&(extent.start as u32).to_le_bytes());
+        record[10..14].copy_from_slice(&(extent.len as u32).to_le_bytes());
+        record[25] = flags;
+        record[32] = name.len() as u8;
+        record[33..33 + name.len()].copy_from_slice(name);
+        record
+    }
+
+    fn joliet(name: &str) -> Vec<u8> {
+        name.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    /// A directory holding `records`, returning its extent.
+    fn directory(image: &mut Image, records: &[Vec<u8>]) -> Extent {
+        let data = records.concat();
+        extent(image.add(&data), data.len())
+    }
+
+    #[test]
+    fn joliet_names_of_a_nested_directory() {
+        let mut image = Image::new();
+        let content = b"install with houdini_installer";
+        let file = extent(image.add(content), content.len());
+        let short = directory(&mut image, &[entry(file, 0, b"README.TXT;1")]);
+        let long = directory(
+            &mut image,
+            &[entry(file, 0, &joliet("Install Notes.txt;1"))],
+        );
+        let short_root = directory(&mut image, &[entry(short, DIRECTORY, b"LAUNCHER")]);
+        let long_root = directory(
+            &mut image,
+            &[entry(long, DIRECTORY, &joliet("Houdini Launcher"))],
+        );
+        image.volume(0, false, short_root);
+        image.volume(1, true, long_root);
+
+        let (files, dir) = image.extract("joliet");
+        assert_eq!(files.unwrap(), 1);
+        let extracted = dir.join("out/Houdini Launcher/Install Notes.txt");
+        assert_eq!(std::fs::read(extracted).unwrap(), content);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn primary_volume_without_joliet() {
+        let mut image = Image::new();
+        let file = extent(image.add(b"x"), 1);
+        let root = directory(&mut image, &[entry(file, 0, b"LICENSE.;1")]);
+        image.volume(0, false, root);
+
+        let (files, dir) = image.extract("primary");
+        assert_eq!(files.unwrap(), 1);
+        assert_eq!(std::fs::read(dir.join("out/LICENSE")).unwrap(), b"x");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn multi_extent_file_is_joined() {
+        let mut image = Image::new();
+        let first = extent(image.add(b"houdini "), 8);
+        let second = extent(image.add(b"launcher"), 8);
+        let name = joliet("launcher.iso");
+        let root = directory(
+            &mut image,
+            &[entry(first, MULTI_EXTENT, &name), entry(second, 0, &name)],
+        );
+        image.volume(0, true, root);
+
+        let (files, dir) = image.extract("multi-extent");
+        assert_eq!(files.unwrap(), 1);
+        let extracted = std::fs::read(dir.join("out/launcher.iso")).unwrap();
+        assert_eq!(extracted, b"houdini launcher");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_image_fails() {
+        let mut image = Image::new();
+        let file = extent(image.add(b"short"), 3 * SECTOR as usize);
+        let root = directory(&mut image, &[entry(file, 0, &joliet("houdini.tar.gz"))]);
+        image.volume(0, true, root);
+
+        let (files, dir) = image.extract("truncated");
+        let error = files.unwrap_err().to_string();
+        assert!(error.contains("truncated"), "{error}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn names_escaping_the_directory_fail() {
+        for name in [
+            joliet(".."),
+            joliet("../escaped"),
+            joliet("a\\b"),
+            b"..;1".to_vec(),
+        ] {
+            let mut image = Image::new();
+            let file = extent(image.add(b"x"), 1);
+            let root = directory(&mut image, &[entry(file, 0, &name)]);
+            image.volume(0, name[0] == 0, root);
+
+            let (files, dir) = image.extract("escape");
+            let error = files.unwrap_err().to_string();
+            assert!(error.contains("Invalid file name"), "{error}");
+            assert!(!dir.join("escaped").exists());
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn corrupt_directories_fail() {
+        let mut image = Image::new();
+        let root = Extent {
+            start: u64::from(image.add(&[0; SECTOR as usize])),
+            len: SECTOR,
+        };
+        // The root lists itself as a subdirectory
+        let record = entry(root, DIRECTORY, &joliet("loop"));
+        image.0[root.start as usize * SECTOR as usize..][..record.len()].copy_from_slice(&record);
+        image.volume(0, true, root);
+        let (files, dir) = image.extract("loop");
+        assert!(files.unwrap_err().to_string().contains("contains itself"));
+        std::fs::remove_dir_all(dir).unwrap();
+
+        // A directory claiming 4 GiB, refused before allocating it
+        let mut image = Image::new();
+        let huge = extent(image.add(&[0; SECTOR as usize]), u32::MAX as usize);
+        image.volume(0, true, huge);
+        let (files, dir) = image.extract("huge");
+        assert!(files.unwrap_err().to_string().contains("directory takes"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
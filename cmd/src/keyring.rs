@@ -0,0 +1,196 @@
+//! `houdl login` and `logout`: the user id and secret in the OS keychain, read when no
+//! credentials are given otherwise. Goes through the keychain's own command line tool,
+//! `security` on macOS and `secret-tool` of libsecret elsewhere, so nothing is linked in and a
+//! headless machine without a keychain just has no stored credentials. Only macOS and Linux
+//! are supported, Windows has no such tool for its credential manager.
+
+use crate::args::Args;
+use anyhow::{bail, Context, Result};
+use dialoguer::{theme::ColorfulTheme, Input, Password};
+use owo_colors::OwoColorize;
+use serde_json::json;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Output, Stdio};
+
+const SERVICE: &str = "houdini-downloader";
+const ACCOUNT: &str = "sidefx.com";
+
+/// `houdl login`: store the credentials piped to stdin or asked for.
+pub fn login(args: &Args) -> Result<()> {
+    check_supported()?;
+    let (user_id, user_secret) = match (&args.user_id, &args.user_secret) {
+        (Some(user_id), Some(user_secret)) if args.credentials_from_stdin => {
+            (user_id.clone(), user_secret.clone())
+        }
+        _ => {
+            if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+                bail!(
+                    "Refusing to prompt without a terminal, \
+                    pass the credentials with --credentials-from-stdin"
+                );
+            }
+            let theme = ColorfulTheme::default();
+            let mut user_id = Input::<String>::with_theme(&theme).with_prompt("SideFX user id");
+            if let Some(current) = &args.user_id {
+                user_id = user_id.with_initial_text(current);
+            }
+            let user_id = user_id.interact_text()?;
+            let user_secret = Password::with_theme(&theme)
+                .with_prompt("SideFX user secret")
+                .interact()?;
+            (user_id.trim().to_string(), user_secret.trim().to_string())
+        }
+    };
+    if user_id.is_empty() || user_secret.is_empty() {
+        bail!("Empty user id or secret");
+    }
+    store(&user_id, &user_secret)?;
+    eprintln!(
+        "{} Stored the credentials in the keychain, they are used when none are given otherwise",
+        "✔".green()
+    );
+    Ok(())
+}
+
+/// `houdl logout`.
+pub fn logout() -> Result<()> {
+    check_supported()?;
+    if delete()? {
+        eprintln!("{} Removed the credentials from the keychain", "✔".green());
+    } else {
+        eprintln!("No credentials stored in the keychain");
+    }
+    Ok(())
+}
+
+/// The stored user id and secret, `None` if there are none or the keychain can't be used.
+pub fn load() -> Option<(String, String)> {
+    if cfg!(windows) {
+        return None;
+    }
+    let output = lookup().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stored: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let field = |name| Some(stored.get(name)?.as_str()?.to_string());
+    Some((field("user_id")?, field("user_secret")?))
+}
+
+/// Store the credentials, replacing any stored before.
+fn store(user_id: &str, user_secret: &str) -> Result<()> {
+    // One item for both, so they can't get out of sync
+    let stored = json!({"user_id": user_id, "user_secret": user_secret}).to_string();
+    let output = if cfg!(target_os = "macos") {
+        // Through the interactive mode, the secret would show up in the process list as an argument
+        let command = format!(
+            "add-generic-password -U -s {SERVICE} -a {ACCOUNT} -X {}\n",
+            stored
+                .bytes()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>()
+        );
+        run(Command::new("security").arg("-i"), Some(&command))?
+    } else {
+        run(
+            Command::new("secret-tool").args([
+                "store",
+                "--label=Houdini downloader",
+                "service",
+                SERVICE,
+                "account",
+                ACCOUNT,
+            ]),
+            Some(&stored),
+        )?
+    };
+    check(output, "store the credentials in")
+}
+
+/// Remove the stored credentials, `false` if there were none.
+fn delete() -> Result<bool> {
+    if !lookup()?.status.success() {
+        return Ok(false);
+    }
+    let output = if cfg!(target_os = "macos") {
+        run(
+            Command::new("security").args([
+                "delete-generic-password",
+                "-s",
+                SERVICE,
+                "-a",
+                ACCOUNT,
+            ]),
+            None,
+        )?
+    } else {
+        run(
+            Command::new("secret-tool").args(["clear", "service", SERVICE, "account", ACCOUNT]),
+            None,
+        )?
+    };
+    check(output, "remove the credentials from").map(|()| true)
+}
+
+fn lookup() -> Result<Output> {
+    if cfg!(target_os = "macos") {
+        run(
+            Command::new("security").args([
+                "find-generic-password",
+                "-s",
+                SERVICE,
+                "-a",
+                ACCOUNT,
+                "-w",
+            ]),
+            None,
+        )
+    } else {
+        run(
+            Command::new("secret-tool").args(["lookup", "service", SERVICE, "account", ACCOUNT]),
+            None,
+        )
+    }
+}
+
+/// Fail before asking for anything where there is no keychain tool to store the credentials.
+fn check_supported() -> Result<()> {
+    if cfg!(windows) {
+        bail!(
+            "Storing credentials isn't supported on Windows, put them in %USERPROFILE%\\_netrc \
+            or set SESI_USER_ID and SESI_USER_SECRET instead"
+        );
+    }
+    Ok(())
+}
+
+/// Run the keychain tool, writing `input` to its stdin.
+fn run(command: &mut Command, input: Option<&str>) -> Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            format!(
+                "Could not run {:?}, no keychain is available",
+                command.get_program()
+            )
+        })?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    if let Some(input) = input {
+        stdin.write_all(input.as_bytes())?;
+    }
+    drop(stdin);
+    Ok(child.wait_with_output()?)
+}
+
+fn check(output: Output, action: &str) -> Result<()> {
+    if !output.status.success() {
+        bail!(
+            "Could not {action} the keychain: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
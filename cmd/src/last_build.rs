@@ -0,0 +1,57 @@
+//! `get --if-newer`: the newest build downloaded per product, platform and version, kept in
+//! `<output_dir>/.last-build` so a scheduled job only downloads when SideFX has a newer one.
+
+use anyhow::{Context, Result};
+use houdini_downloader_api::{Platform, Product};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const FILENAME: &str = ".last-build";
+
+/// Recorded builds, by [`LastBuilds::key`].
+pub struct LastBuilds {
+    path: PathBuf,
+    builds: BTreeMap<String, u64>,
+}
+
+impl LastBuilds {
+    /// Read the state file in `dir`. A missing one records nothing, a corrupt one is ignored
+    /// with a warning, so every build counts as newer.
+    pub fn load(dir: &Path) -> LastBuilds {
+        let path = dir.join(FILENAME);
+        let builds = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                eprintln!(
+                    "[warning]: Ignoring unreadable {}: {e}",
+                    path.to_string_lossy()
+                );
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        LastBuilds { path, builds }
+    }
+
+    pub fn key(product: Product, platform: &Platform, version: &str) -> String {
+        format!("{product}/{platform}/{version}")
+    }
+
+    /// The recorded build for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<u64> {
+        self.builds.get(key).copied()
+    }
+
+    /// Whether `build` is newer than the one recorded for `key`.
+    pub fn is_newer(&self, key: &str, build: u64) -> bool {
+        self.get(key).is_none_or(|last| build > last)
+    }
+
+    /// Record `build` for `key` unless a newer one is recorded, and save the file.
+    pub fn record(&mut self, key: String, build: u64) -> Result<()> {
+        let last = self.builds.entry(key).or_insert(build);
+        *last = (*last).max(build);
+        let data = serde_json::to_vec_pretty(&self.builds)?;
+        std::fs::write(&self.path, data)
+            .with_context(|| format!("Could not write {}", self.path.to_string_lossy()))
+    }
+}
@@ -0,0 +1,624 @@
+use crate::args::{OutputFormat, ProductArg};
+use crate::template::Template;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use futures_util::StreamExt;
+use houdini_downloader_api::{
+    format_bytes, Build, ListBuildsParms, Platform, Product, ReleaseChannel, SesiClient,
+};
+use owo_colors::{AnsiColors, OwoColorize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Default `list --limit` when daily builds are included, which are hundreds.
+pub const DAILY_BUILDS_LIMIT: usize = 25;
+
+/// The `--limit` in effect, `None` for all builds: `0` lifts it, and without one a text listing
+/// with daily builds stops at [`DAILY_BUILDS_LIMIT`].
+pub fn effective_limit(
+    limit: Option<usize>,
+    include_daily_builds: bool,
+    format: OutputFormat,
+) -> Option<usize> {
+    match limit {
+        Some(0) => None,
+        Some(limit) => Some(limit),
+        None if include_daily_builds && format == OutputFormat::Text => Some(DAILY_BUILDS_LIMIT),
+        None => None,
+    }
+}
+
+/// Cut `items` to `limit`, returning how many were left out.
+pub fn truncate<T>(items: &mut Vec<T>, limit: Option<usize>) -> usize {
+    match limit {
+        Some(limit) if items.len() > limit => items.split_off(limit).len(),
+        _ => 0,
+    }
+}
+
+/// Tell on stderr that `hidden` builds were left out by `--limit`.
+pub fn print_hidden_note(hidden: usize) {
+    if let Some(note) = hidden_note(hidden) {
+        eprintln!("{note}");
+    }
+}
+
+fn hidden_note(hidden: usize) -> Option<String> {
+    (hidden > 0).then(|| format!("… and {hidden} more (use --limit 0 for all)"))
+}
+
+/// Print builds to stdout. `sizes` is either empty or has one entry per build.
+/// `color` and `relative_dates` are only used by the text table.
+pub fn print_builds(
+    builds: &[Build],
+    sizes: &[Option<u64>],
+    format: OutputFormat,
+    color: bool,
+    relative_dates: bool,
+) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        OutputFormat::Text => write_text(&mut stdout, builds, sizes, color, relative_dates),
+        OutputFormat::Json => write_json(&mut stdout, builds, sizes),
+        OutputFormat::Csv => write_csv(&mut stdout, builds, sizes),
+        OutputFormat::Ndjson => {
+            for (i, build) in builds.iter().enumerate() {
+                write_ndjson_line(&mut stdout, build, sizes.get(i).copied())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One line describing the builds, like "47 builds (45 gold, 2 beta), 3 bad, 20.0.400–20.0.650".
+/// `None` without builds.
+pub fn summary<'a>(builds: impl IntoIterator<Item = &'a Build>) -> Option<String> {
+    let builds: Vec<&Build> = builds.into_iter().collect();
+    if builds.is_empty() {
+        return None;
+    }
+    // Gold first, then beta, then whatever else SideFX calls its releases
+    let mut channels: BTreeMap<(u8, String), usize> = BTreeMap::new();
+    for build in &builds {
+        let channel = match build.release_channel() {
+            ReleaseChannel::Gold => (0, "gold".to_string()),
+            ReleaseChannel::Beta => (1, "beta".to_string()),
+            ReleaseChannel::Other(release) if release.is_empty() => (2, "other".to_string()),
+            ReleaseChannel::Other(release) => (2, release),
+        };
+        *channels.entry(channel).or_default() += 1;
+    }
+    let channels: Vec<String> = channels
+        .into_iter()
+        .map(|((_, channel), count)| format!("{count} {channel}"))
+        .collect();
+    let count = builds.len();
+    let s = if count == 1 { "" } else { "s" };
+    let bad = builds.iter().filter(|b| b.is_bad()).count();
+    let mut line = format!("{count} build{s} ({}), {bad} bad", channels.join(", "));
+    let versions = || builds.iter().filter(|b| b.version_tuple().is_some());
+    let oldest = versions().min_by_key(|b| b.version_tuple());
+    let newest = versions().max_by_key(|b| b.version_tuple());
+    match (oldest, newest) {
+        (Some(oldest), Some(newest)) if oldest.version_tuple() == newest.version_tuple() => {
+            line.push_str(&format!(", {}", oldest.full_version()));
+        }
+        (Some(oldest), Some(newest)) => {
+            let (oldest, newest) = (oldest.full_version(), newest.full_version());
+            line.push_str(&format!(", spanning {oldest}–{newest}"));
+        }
+        _ => {}
+    }
+    Some(line)
+}
+
+/// Print the [`summary`] line below the text table, dimmed when `color` is set.
+pub fn print_summary(summary: Option<String>, color: bool) -> Result<()> {
+    let Some(summary) = summary else {
+        return Ok(());
+    };
+    let mut stdout = std::io::stdout().lock();
+    if color {
+        writeln!(stdout, "{}", summary.dimmed())?;
+    } else {
+        writeln!(stdout, "{summary}")?;
+    }
+    Ok(())
+}
+
+/// Print a line per build, formatted by `template`.
+pub fn print_template(builds: &[Build], template: &Template) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    for build in builds {
+        writeln!(stdout, "{}", template.render(build))?;
+    }
+    Ok(())
+}
+
+pub fn print_versions(versions: &[String], format: OutputFormat) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        OutputFormat::Text => {
+            for version in versions {
+                writeln!(stdout, "{version}")?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, versions)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(stdout, "version")?;
+            for version in versions {
+                writeln!(stdout, "{}", csv_field(version))?;
+            }
+        }
+        OutputFormat::Ndjson => {
+            for version in versions {
+                serde_json::to_writer(&mut stdout, version)?;
+                writeln!(stdout)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the products of `--product` with the identifier SideFX uses for each. They're the
+/// ones this build knows, SideFX has no endpoint listing them.
+pub fn print_products(format: OutputFormat) -> Result<()> {
+    let products: Vec<_> = ProductArg::value_variants()
+        .iter()
+        .map(|arg| {
+            let name = arg
+                .to_possible_value()
+                .expect("no product is skipped")
+                .get_name()
+                .to_string();
+            (name, Product::from(*arg))
+        })
+        .collect();
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        OutputFormat::Text => {
+            let width = products
+                .iter()
+                .map(|(name, _)| name.len())
+                .max()
+                .unwrap_or(0);
+            let api_width = products
+                .iter()
+                .map(|(_, product)| product.as_api_str().len())
+                .max()
+                .unwrap_or(0);
+            writeln!(
+                stdout,
+                "{:<width$}  {:<api_width$}  description",
+                "product", "api"
+            )?;
+            for (name, product) in &products {
+                writeln!(
+                    stdout,
+                    "{name:<width$}  {:<api_width$}  {}",
+                    product.as_api_str(),
+                    product.description()
+                )?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(stdout, "product,api,description")?;
+            for (name, product) in &products {
+                writeln!(
+                    stdout,
+                    "{},{},{}",
+                    csv_field(name),
+                    csv_field(product.as_api_str()),
+                    csv_field(product.description())
+                )?;
+            }
+        }
+        OutputFormat::Json | OutputFormat::Ndjson => {
+            let values: Vec<_> = products
+                .iter()
+                .map(|(name, product)| {
+                    serde_json::json!({
+                        "product": name,
+                        "api": product.as_api_str(),
+                        "description": product.description(),
+                    })
+                })
+                .collect();
+            if format == OutputFormat::Json {
+                serde_json::to_writer_pretty(&mut stdout, &values)?;
+                writeln!(stdout)?;
+            } else {
+                for value in values {
+                    serde_json::to_writer(&mut stdout, &value)?;
+                    writeln!(stdout)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print builds as NDJSON while the listing is still being received.
+pub async fn stream_ndjson(
+    client: &SesiClient,
+    product: Product,
+    platforms: Vec<Platform>,
+    version: Option<String>,
+    only_production: bool,
+    filter: impl Fn(&Build) -> bool,
+    with_size: bool,
+) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    for platform in platforms {
+        let parms = ListBuildsParms {
+            product,
+            platform,
+            version: version.clone(),
+            only_production,
+        };
+        let builds = client
+            .list_builds_stream(parms)
+            .await
+            .context("Error encountered when trying to list available builds")?;
+        let mut builds = std::pin::pin!(builds);
+        while let Some(build) = builds.next().await {
+            let build = build.context("Error encountered when trying to list available builds")?;
+            if !filter(&build) {
+                continue;
+            }
+            let size = if with_size {
+                Some(
+                    client
+                        .get_build_url_for(&build)
+                        .await
+                        .ok()
+                        .map(|url| url.size),
+                )
+            } else {
+                None
+            };
+            write_ndjson_line(&mut stdout, &build, size)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn write_ndjson_line(out: &mut impl Write, build: &Build, size: Option<Option<u64>>) -> Result<()> {
+    let mut value = serde_json::to_value(build)?;
+    if let Some(size) = size {
+        value["size"] = serde_json::json!(size);
+    }
+    serde_json::to_writer(&mut *out, &value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_text(
+    out: &mut impl Write,
+    builds: &[Build],
+    sizes: &[Option<u64>],
+    color: bool,
+    relative_dates: bool,
+) -> Result<()> {
+    let date = date_cell(relative_dates);
+    let mut header = vec![
+        "#", "Date", "Platform", "Version", "Build", "Status", "Release",
+    ];
+    if !sizes.is_empty() {
+        header.push("Size");
+    }
+    let rows: Vec<(&Build, Vec<String>)> = builds
+        .iter()
+        .enumerate()
+        .map(|(i, build)| {
+            let mut row = vec![
+                i.to_string(),
+                date(build),
+                build.platform.clone(),
+                build.version.clone(),
+                build.build.to_string(),
+                build.status.clone(),
+                build.release.clone(),
+            ];
+            match sizes.get(i) {
+                Some(Some(size)) => row.push(format_bytes(*size)),
+                Some(None) => row.push("?".to_string()),
+                None => {}
+            }
+            (build, row)
+        })
+        .collect();
+    write_table(out, &header, &rows, &[0, 4], 5, color)
+}
+
+/// Format the date column of the text table, as is or relative to today.
+fn date_cell(relative: bool) -> impl Fn(&Build) -> String {
+    let today = relative.then(|| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        (now / 86400) as i64
+    });
+    move |build| match today {
+        Some(today) => relative_date(build, today),
+        None => build.date.clone(),
+    }
+}
+
+/// The build date as "today", "yesterday", "3 days ago", "2 weeks ago", "5 months ago" or
+/// "2 years ago", `today` being days since 1970-01-01. Dates that don't parse, or lie in the
+/// future, are returned as they are.
+fn relative_date(build: &Build, today: i64) -> String {
+    let date = &build.date;
+    let Some(days) = build.date_days().map(|day| today - day) else {
+        return date.to_string();
+    };
+    let ago = |count: i64, unit: &str| {
+        let s = if count == 1 { "" } else { "s" };
+        format!("{count} {unit}{s} ago")
+    };
+    match days {
+        ..=-1 => date.to_string(),
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        2..=13 => ago(days, "day"),
+        14..=59 => ago(days / 7, "week"),
+        60..=364 => ago(days / 30, "month"),
+        _ => ago(days / 365, "year"),
+    }
+}
+
+/// Aligned table, columns sized to their content. The `numeric` columns are right aligned,
+/// the `status` column is colored by the status of the row's build.
+fn write_table(
+    out: &mut impl Write,
+    header: &[&str],
+    rows: &[(&Build, Vec<String>)],
+    numeric: &[usize],
+    status: usize,
+    color: bool,
+) -> Result<()> {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for (_, row) in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for (i, (cell, width)) in header.iter().zip(&widths).enumerate() {
+        let cell = format!("{cell:<width$}");
+        let sep = if i + 1 == widths.len() { "" } else { "  " };
+        if color {
+            write!(out, "{}{sep}", cell.bold())?;
+        } else {
+            write!(out, "{cell}{sep}")?;
+        }
+    }
+    writeln!(out)?;
+    for (build, row) in rows {
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            let cell = if numeric.contains(&i) {
+                format!("{cell:>width$}")
+            } else {
+                format!("{cell:<width$}")
+            };
+            let sep = if i + 1 == widths.len() { "" } else { "  " };
+            match status_color(build) {
+                Some(status_color) if color && i == status => {
+                    write!(out, "{}{sep}", cell.color(status_color))?
+                }
+                _ => write!(out, "{cell}{sep}")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// A build listed for several platforms, shown once by `list --group-by-build`.
+pub struct BuildGroup {
+    /// The first platform's listing, for the fields the platforms share.
+    pub build: Build,
+    /// SideFX identifiers of the platforms it's available on.
+    pub platforms: Vec<String>,
+}
+
+/// Join builds of different platforms on version and build number, keeping the order of the
+/// first platform listing each.
+pub fn group_by_build(builds: Vec<Build>) -> Vec<BuildGroup> {
+    let mut groups: Vec<BuildGroup> = Vec::new();
+    for build in builds {
+        let platform = Platform::from_build_str(&build.platform).to_string();
+        let group = groups
+            .iter_mut()
+            .find(|g| g.build.build == build.build && g.build.version == build.version);
+        match group {
+            Some(group) if group.platforms.contains(&platform) => {}
+            Some(group) => group.platforms.push(platform),
+            None => groups.push(BuildGroup {
+                build,
+                platforms: vec![platform],
+            }),
+        }
+    }
+    groups
+}
+
+/// Print grouped builds to stdout, with the platforms in place of the platform column.
+pub fn print_groups(
+    groups: &[BuildGroup],
+    format: OutputFormat,
+    color: bool,
+    relative_dates: bool,
+) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    let values = || {
+        groups.iter().map(|group| {
+            let mut value = serde_json::to_value(&group.build)?;
+            if let Some(fields) = value.as_object_mut() {
+                fields.remove("platform");
+            }
+            value["platforms"] = serde_json::json!(group.platforms);
+            Ok::<_, serde_json::Error>(value)
+        })
+    };
+    match format {
+        OutputFormat::Text => {
+            let header = [
+                "#",
+                "Date",
+                "Version",
+                "Build",
+                "Status",
+                "Release",
+                "Platforms",
+            ];
+            let date = date_cell(relative_dates);
+            let rows: Vec<(&Build, Vec<String>)> = groups
+                .iter()
+                .enumerate()
+                .map(|(i, group)| {
+                    let build = &group.build;
+                    let row = vec![
+                        i.to_string(),
+                        date(build),
+                        build.version.clone(),
+                        build.build.to_string(),
+                        build.status.clone(),
+                        build.release.clone(),
+                        group.platforms.join(", "),
+                    ];
+                    (build, row)
+                })
+                .collect();
+            write_table(&mut stdout, &header, &rows, &[0, 3], 4, color)?;
+        }
+        OutputFormat::Json => {
+            let values = values().collect::<Result<Vec<_>, _>>()?;
+            serde_json::to_writer_pretty(&mut stdout, &values)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(stdout, "index,date,version,build,status,release,platforms")?;
+            for (i, group) in groups.iter().enumerate() {
+                let build = &group.build;
+                writeln!(
+                    stdout,
+                    "{i},{},{},{},{},{},{}",
+                    csv_field(&build.date),
+                    csv_field(&build.version),
+                    build.build,
+                    csv_field(&build.status),
+                    csv_field(&build.release),
+                    csv_field(&group.platforms.join(",")),
+                )?;
+            }
+        }
+        OutputFormat::Ndjson => {
+            for value in values() {
+                serde_json::to_writer(&mut stdout, &value?)?;
+                writeln!(stdout)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn status_color(build: &Build) -> Option<AnsiColors> {
+    match build.status.as_str() {
+        "good" => Some(AnsiColors::Green),
+        "bad" => Some(AnsiColors::Red),
+        _ => None,
+    }
+}
+
+fn write_json(out: &mut impl Write, builds: &[Build], sizes: &[Option<u64>]) -> Result<()> {
+    let mut values = Vec::with_capacity(builds.len());
+    for (i, build) in builds.iter().enumerate() {
+        let mut value = serde_json::to_value(build)?;
+        if let Some(size) = sizes.get(i) {
+            value["size"] = serde_json::json!(size);
+        }
+        values.push(value);
+    }
+    serde_json::to_writer_pretty(&mut *out, &values)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_csv(out: &mut impl Write, builds: &[Build], sizes: &[Option<u64>]) -> Result<()> {
+    let with_size = !sizes.is_empty();
+    write!(out, "index,date,platform,version,build,status,release")?;
+    if with_size {
+        write!(out, ",size")?;
+    }
+    writeln!(out)?;
+    for (i, build) in builds.iter().enumerate() {
+        write!(
+            out,
+            "{i},{},{},{},{},{},{}",
+            csv_field(&build.date),
+            csv_field(&build.platform),
+            csv_field(&build.version),
+            build.build,
+            csv_field(&build.status),
+            csv_field(&build.release),
+        )?;
+        if with_size {
+            write!(out, ",")?;
+            if let Some(Some(size)) = sizes.get(i) {
+                write!(out, "{size}")?;
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Quote a field if it contains a separator, quote or line break.
+pub fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_builds_are_capped_by_default() {
+        let text = OutputFormat::Text;
+        assert_eq!(effective_limit(None, true, text), Some(DAILY_BUILDS_LIMIT));
+        assert_eq!(effective_limit(None, false, text), None);
+        assert_eq!(effective_limit(None, true, OutputFormat::Json), None);
+        assert_eq!(effective_limit(Some(10), false, text), Some(10));
+        assert_eq!(effective_limit(Some(0), true, text), None);
+    }
+
+    #[test]
+    fn truncation_counts_the_hidden_builds() {
+        let mut builds: Vec<u32> = (0..30).collect();
+        assert_eq!(truncate(&mut builds, Some(DAILY_BUILDS_LIMIT)), 5);
+        assert_eq!(builds.len(), DAILY_BUILDS_LIMIT);
+        assert_eq!(truncate(&mut builds, Some(100)), 0);
+        assert_eq!(truncate(&mut builds, None), 0);
+        assert_eq!(builds.len(), DAILY_BUILDS_LIMIT);
+    }
+
+    #[test]
+    fn hidden_note_wording() {
+        assert_eq!(hidden_note(0), None);
+        assert_eq!(
+            hidden_note(5).as_deref(),
+            Some("… and 5 more (use --limit 0 for all)")
+        );
+    }
+}
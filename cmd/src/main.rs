@@ -1,39 +1,214 @@
 mod args;
+mod desktop;
+mod diff;
+mod disk;
+mod doctor;
+mod download;
+mod download_log;
+mod dump_config;
+#[cfg(feature = "iso")]
+mod iso;
+#[cfg(feature = "keyring")]
+mod keyring;
+mod last_build;
+mod list;
+mod pause;
+mod progress_fd;
+mod self_test;
+mod template;
 
-use crate::args::{Args, Commands};
+use crate::args::{Args, BuildList, ChannelArg, Commands, OutputFormat, SortArg, VersionArg};
+use crate::download::{BatchProgress, BuildId, DownloadOptions};
+use crate::last_build::LastBuilds;
 use anyhow::{bail, Context, Result};
 use dialoguer::{theme::ColorfulTheme, Confirm};
-use futures_util::StreamExt;
-use houdini_downloader_api::SesiClient;
-use indicatif::ProgressStyle;
-use md5::{Digest, Md5};
+use houdini_downloader_api::{
+    format_bytes, ApiError, Build, BuildUrl, ClientConfig, Kind, Platform, Product, RateLimitFn,
+    SesiClient, Version,
+};
 use owo_colors::{AnsiColors, OwoColorize};
-use std::io::Write;
-use tokio::io::{AsyncWriteExt, BufWriter};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Read};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
-    let args: Args = Args::parse_();
+/// Exit code when the requested build doesn't exist, see [`Kind::NotFound`].
+const EXIT_NOT_FOUND: u8 = 3;
 
-    if args.user_id.is_none() || args.user_secret.is_none() {
-        bail!("SESI_USER_ID and SESI_USER_SECRET are required");
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            let not_found = e
+                .chain()
+                .filter_map(|e| e.downcast_ref::<ApiError>())
+                .any(|e| e.kind() == Kind::NotFound);
+            ExitCode::from(if not_found { EXIT_NOT_FOUND } else { 1 })
+        }
     }
+}
 
-    if !args.commands.is_version_valid() {
-        bail!("Version number must be major.minor [e.g 19.5]")
+async fn run() -> Result<()> {
+    let mut args: Args = Args::parse_();
+    if args.credentials_from_stdin {
+        if matches!(args.commands, Commands::DownloadUrl { url_file: None, .. }) {
+            bail!("--credentials-from-stdin needs the download URL JSON in a file, not on stdin");
+        }
+        args.read_credentials_from_stdin()?;
     }
-
-    // None variants were checked above
-    let user_id = args.user_id.as_deref().unwrap();
-    let user_secret = args.user_secret.as_deref().unwrap();
+    let product: Product = args.product.into();
+    #[cfg(feature = "keyring")]
+    match args.commands {
+        Commands::Login => return keyring::login(&args),
+        Commands::Logout => return keyring::logout(),
+        _ => {}
+    }
+    if let Commands::Info = args.commands {
+        return print_info(&args);
+    }
+    if let Commands::Products { format } = args.commands {
+        return list::print_products(format);
+    }
+    let platform = args.platform();
 
     ctrlc::set_handler(move || {
-        println!("Killed with CTRL-C");
+        eprintln!("Killed with CTRL-C");
         std::process::exit(0);
     })
     .context("Error setting up CTRL-C handler")?;
 
-    let client = SesiClient::new(user_id, user_secret)
+    let mut config = ClientConfig {
+        max_concurrent_requests: args.jobs,
+        api_retries: args.api_retries,
+        danger_accept_invalid_certs: args.insecure,
+        cache_dir: args.cache_dir.clone(),
+        token_cache: !args.no_cache,
+        listing_cache: !args.no_cache,
+        trace_http: args.trace_http,
+        max_redirects: args.max_redirects,
+        pool_max_idle_per_host: args.pool_max_idle_per_host,
+        pool_idle_timeout: args.pool_idle_timeout.map(Duration::from_secs),
+        http1_only: args.http1_only,
+        on_rate_limit: Some(RateLimitFn(Arc::new(|limited| {
+            let reason = if limited.retry_after {
+                "Retry-After"
+            } else {
+                "backoff"
+            };
+            eprintln!(
+                "Rate limited by SideFX, waiting {}s ({reason}) …",
+                limited.wait.as_secs()
+            );
+        }))),
+        ..ClientConfig::default()
+    };
+    if let Some(user_agent) = args.user_agent.clone() {
+        config.user_agent = user_agent;
+    }
+    for (name, value) in &args.headers {
+        config.headers.append(name.clone(), value.clone());
+    }
+    for path in &args.cacert {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("Could not read certificate {}", path.to_string_lossy()))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .with_context(|| format!("Invalid PEM certificate {}", path.to_string_lossy()))?;
+        config.root_certificates.push(cert);
+    }
+    if let Some(format) = args.dump_config {
+        return dump_config::print(&args, product, &config, format);
+    }
+    if args.insecure {
+        eprintln!(
+            "{}",
+            "[warning]: TLS certificate validation is disabled (--insecure), \
+            connections can be intercepted"
+                .color(AnsiColors::Red)
+        );
+    }
+
+    if let Commands::DownloadUrl { url_file, download } = &args.commands {
+        let mut opts = DownloadOptions::from(download);
+        opts.auto_confirm |= args.credentials_from_stdin;
+        let opts = &opts;
+        let json = match url_file {
+            Some(path) => std::fs::read(path)
+                .with_context(|| format!("Could not read {}", path.to_string_lossy()))?,
+            None => {
+                let mut json = Vec::new();
+                std::io::stdin()
+                    .read_to_end(&mut json)
+                    .context("Could not read download URL from stdin")?;
+                json
+            }
+        };
+        let json: serde_json::Value =
+            serde_json::from_slice(&json).context("Invalid download URL JSON")?;
+        let build_info = serde_json::from_value::<BuildUrl>(json.clone()).context(
+            "Invalid download URL JSON, expected an object with \
+            download_url, filename, hash and size",
+        )?;
+        let http = config.http_client()?;
+        // stdin is taken by the JSON, so there is nothing to read a confirmation from
+        let confirm = !opts.auto_confirm && url_file.is_some();
+        // `get --dry-run` adds the build next to the URL
+        let id = BuildId::from_json(&json);
+        let filename = download::saved_filename(&build_info, id.as_ref(), opts);
+        return match download::download_build(&http, build_info, id.as_ref(), opts, confirm).await {
+            Err(e) if download::is_url_expired(&e) => {
+                let (Some(user_id), Some(user_secret), Some(id)) =
+                    (args.user_id.as_deref(), args.user_secret.as_deref(), id)
+                else {
+                    let e = e.context(
+                        "The download URL expired, run `get --dry-run` again for a fresh one",
+                    );
+                    download::print_failure(opts, &filename, &e);
+                    return Err(e);
+                };
+                opts.status(format!("Download URL expired, resolving {id} again"));
+                let client = SesiClient::with_config(user_id, user_secret, config)
+                    .await
+                    .context("Error encountered while trying to authorize with SideFX")?;
+                let build_info = client
+                    .get_build_url(id.product, id.platform.clone(), &id.version, id.build)
+                    .await
+                    .map_err(|e| build_info_error(e, &id.version, id.build, &id.platform))?;
+                download::download_build(client.http_client(), build_info, Some(&id), opts, false)
+                    .await
+            }
+            result => result,
+        };
+    }
+
+    if let Commands::SelfTest { silent } = args.commands {
+        return self_test::run(silent).await;
+    }
+
+    if let Commands::Doctor = args.commands {
+        return doctor::run(
+            config,
+            args.user_id.as_deref(),
+            args.user_secret.as_deref(),
+            product,
+            platform,
+        )
+        .await;
+    }
+
+    if args.user_id.is_none() || args.user_secret.is_none() {
+        bail!("SESI_USER_ID and SESI_USER_SECRET are required, or a sidefx.com entry in ~/.netrc");
+    }
+
+    let platform = platform?;
+    // None variants were checked above
+    let user_id = args.user_id.as_deref().unwrap();
+    let user_secret = args.user_secret.as_deref().unwrap();
+
+    let client = SesiClient::with_config(user_id, user_secret, config)
         .await
         .context("Error encountered while trying to authorize with SideFX")?;
 
@@ -41,111 +216,650 @@ async fn main() -> Result<()> {
         Commands::Get {
             version,
             build,
-            output_dir,
-            silent,
-            overwrite,
+            latest,
+            prefer_good,
+            channel,
+            download,
+            dry_run,
+            allow_bad,
+            yes,
+            continue_on_error,
+            write_metadata,
+            if_newer,
         } => {
-            let build_info = client
-                .get_build_url(args.product.into(), args.platform.into(), version, build)
-                .await
-                .context("Error encountered while trying to get build info")?;
-            let filename = &build_info.filename;
-            let output = output_dir.join(filename);
-            if !overwrite && output.exists() {
-                eprintln!("File already downloaded: {}", output.to_string_lossy());
-                return Ok(());
+            let mut opts = DownloadOptions::from(&download);
+            // stdin held the credentials, there is nothing to read a confirmation from
+            opts.auto_confirm |= args.credentials_from_stdin;
+            if prefer_good && !latest {
+                bail!("--prefer-good only applies to --latest");
             }
-            if !silent {
-                let confirmation = Confirm::with_theme(&ColorfulTheme::default())
-                    .with_prompt(format!("Download {filename}?"))
-                    .interact_opt()?;
-                match confirmation {
-                    None => return Ok(()),
-                    Some(inp) if !inp => return Ok(()),
-                    _ => {}
+            let build = match (version.and_then(|v| v.build()), build) {
+                (Some(_), _) if latest => bail!("--latest can't be used with a build number"),
+                (Some(_), Some(_)) => {
+                    bail!("Give the build number either in --version or with --build, not both")
                 }
-            }
-            let response = reqwest::get(build_info.download_url)
-                .await
-                .context("Could not send GET download request")?;
-            let downloading_started_msg = format!("Downloading {}", filename);
-            let bar = if !silent {
-                let bar = indicatif::ProgressBar::new(build_info.size);
-                bar.set_style(
-                    ProgressStyle::default_bar()
-                        .template(
-                            "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] \
-                            {bytes}/{total_bytes} ({binary_bytes_per_sec}, {eta})",
-                        )?
-                        .progress_chars("#>-"),
-                );
-                bar.set_message(downloading_started_msg);
-                Some(bar)
-            } else {
-                println!("{}", downloading_started_msg);
-                None
+                (Some(build), None) => Some(BuildList(vec![build])),
+                (None, None) if !latest => {
+                    bail!("--build is required unless --latest is given or --version includes it [e.g. 19.5.805]")
+                }
+                (None, build) => build,
             };
-            let file = tokio::fs::File::create(&output)
-                .await
-                .context("Could not create file to save")?;
-            let mut file_buf = BufWriter::new(file);
-            let mut stream = response.bytes_stream();
-            let mut hash = Md5::new();
-            while let Some(chunk) = stream.next().await {
-                if let Ok(bytes) = chunk {
-                    file_buf
-                        .write_all(&bytes)
+            let version = match version {
+                Some(version) => Some(resolve_version(&client, product, &platform, version).await?),
+                None => None,
+            };
+            let targets = match (version, build) {
+                (Some(version), Some(builds)) if !latest => {
+                    if builds.0.len() > BuildList::CONFIRM_ABOVE && !yes {
+                        bail!(
+                            "{} builds selected, pass --yes to download more than {} at once",
+                            builds.0.len(),
+                            BuildList::CONFIRM_ABOVE
+                        );
+                    }
+                    let mut listed: HashMap<u64, Build> = client
+                        .list_builds(product, platform.clone(), Some(&version), false)
                         .await
-                        .context("Error writing to output file")?;
-                    hash.update(&bytes);
-                    if let Some(ref bar) = bar {
-                        bar.inc(bytes.len() as u64);
+                        .context("Error encountered when trying to look up the build")?
+                        .into_iter()
+                        .map(|b| (b.build, b))
+                        .collect();
+                    builds
+                        .0
+                        .into_iter()
+                        .map(|build| (version.clone(), build, listed.remove(&build)))
+                        .collect()
+                }
+                (version, _) => {
+                    let only_production = channel != ChannelArg::Beta;
+                    let builds = client
+                        .list_builds(product, platform.clone(), version, only_production)
+                        .await
+                        .context("Error encountered when trying to list available builds")?;
+                    let mut builds: Vec<Build> =
+                        builds.into_iter().filter(|b| channel.matches(b)).collect();
+                    if prefer_good {
+                        let newest = builds.iter().max_by_key(|b| b.version_tuple()).cloned();
+                        builds.retain(Build::is_good);
+                        if let (Some(newest), true) = (newest, builds.is_empty()) {
+                            bail!(
+                                "No build marked good found, the newest is {} with status {:?}",
+                                newest.full_version(),
+                                newest.status
+                            );
+                        }
                     }
+                    let Some(latest) = builds.into_iter().max_by_key(Build::version_tuple) else {
+                        bail!("No builds found");
+                    };
+                    opts.status(format!("Latest build: {latest}"));
+                    vec![(latest.version.clone(), latest.build, Some(latest))]
                 }
+            };
+            let mut last_builds = if_newer.then(|| LastBuilds::load(&opts.output_dir));
+            let targets: Vec<_> = match &last_builds {
+                Some(last_builds) => targets
+                    .into_iter()
+                    .filter(|(version, build, _)| {
+                        let key = LastBuilds::key(product, &platform, version);
+                        let newer = last_builds.is_newer(&key, *build);
+                        if let Some(last) = last_builds.get(&key).filter(|_| !newer) {
+                            opts.status(format!(
+                                "Skipping {version}.{build}, not newer than the last downloaded {version}.{last}"
+                            ));
+                        }
+                        newer
+                    })
+                    .collect(),
+                None => targets,
+            };
+            if if_newer && targets.is_empty() {
+                opts.status("Already up to date");
+                return Ok(());
             }
-            if let Some(bar) = bar {
-                bar.finish_with_message(format!("Downloaded: {}", output.to_string_lossy()));
+            let many = targets.len() > 1;
+            let total = targets.len();
+            let mut succeeded = Vec::new();
+            let mut failed = Vec::new();
+            let mut wanted = Vec::new();
+            for (version, build, listed) in targets {
+                let confirmed = match listed.as_ref().filter(|b| b.is_bad()) {
+                    Some(listed) if !dry_run => {
+                        confirm_bad_build(listed, opts.auto_confirm, allow_bad)
+                    }
+                    _ => Ok(true),
+                };
+                match confirmed {
+                    Ok(true) => wanted.push((version, build, listed)),
+                    Ok(false) => {}
+                    Err(e) if continue_on_error => report_failed(&version, build, e, &mut failed),
+                    Err(e) => return Err(e),
+                }
             }
-            let downloaded_bytes_hash = hex::encode(&hash.finalize());
-            println!("Build md5 checksum: {}", &downloaded_bytes_hash.green());
-            if downloaded_bytes_hash != build_info.hash {
-                eprintln!(
-                    "{}",
-                    "[warning]: Downloaded file hash is different from the build hash"
-                        .color(AnsiColors::Red)
+            // Several targets are always builds of the same version
+            let version = wanted
+                .first()
+                .map_or_else(String::new, |(v, _, _)| v.clone());
+            let urls = client
+                .get_build_urls(
+                    product,
+                    platform.clone(),
+                    &version,
+                    wanted.iter().map(|(_, build, _)| *build),
                 )
+                .await;
+            let mut resolved = Vec::new();
+            for ((version, build, listed), url) in wanted.into_iter().zip(urls) {
+                match url.map_err(|e| build_info_error(e, &version, build, &platform)) {
+                    Ok(build_info) => resolved.push((version, build, listed, build_info)),
+                    Err(e) if continue_on_error => report_failed(&version, build, e, &mut failed),
+                    Err(e) => return Err(e),
+                }
+            }
+            if dry_run {
+                let mut summaries = Vec::new();
+                for (version, build, _, build_info) in &resolved {
+                    let mut summary = serde_json::to_value(build_info)?;
+                    summary["product"] = product.as_api_str().into();
+                    summary["platform"] = platform.as_api_str().into();
+                    summary["version"] = version.as_str().into();
+                    summary["build"] = (*build).into();
+                    summary["resumable"] = houdini_downloader_api::download::is_resumable(
+                        client.http_client(),
+                        build_info,
+                    )
+                    .await
+                    .into();
+                    summaries.push(summary);
+                }
+                // Nothing resolved with --continue-on-error, the failures are reported below
+                if let Some(summaries) = dry_run_json(summaries, many) {
+                    println!("{}", serde_json::to_string_pretty(&summaries)?);
+                }
+            } else if many {
+                let needed: u64 = resolved.iter().map(|(_, _, _, b)| b.size).sum();
+                let margin = opts.min_free_space.unwrap_or(0);
+                if let Some(available) = disk::available_space(&opts.output_dir) {
+                    eprintln!(
+                        "{} builds, {} in total, {} available",
+                        resolved.len(),
+                        format_bytes(needed),
+                        format_bytes(available)
+                    );
+                    if needed.saturating_add(margin) > available {
+                        bail!(
+                            "Not enough disk space in {} for {} of builds and {} kept free",
+                            opts.output_dir.to_string_lossy(),
+                            format_bytes(needed),
+                            format_bytes(margin)
+                        );
+                    }
+                }
+                // Ask once for the whole selection instead of once per build
+                if !opts.auto_confirm {
+                    let confirmation = download::ask(
+                        Confirm::with_theme(&ColorfulTheme::default()).with_prompt(format!(
+                            "Download {} builds ({})?",
+                            resolved.len(),
+                            format_bytes(needed)
+                        )),
+                    )?;
+                    if confirmation != Some(true) {
+                        return Ok(());
+                    }
+                }
+                opts.batch = (!opts.no_progress).then(|| BatchProgress::new(needed));
+            }
+            if !dry_run {
+                let confirm = !opts.auto_confirm && !many;
+                let mut processed = 0;
+                for (version, build, listed, build_info) in resolved {
+                    opts.status(format!(
+                        "Resolved {product} {platform} {version}.{build}: {}, {}, md5 {}",
+                        build_info.filename,
+                        format_bytes(build_info.size),
+                        build_info.hash
+                    ));
+                    let id = BuildId {
+                        product,
+                        platform: platform.clone(),
+                        version: version.clone(),
+                        build,
+                    };
+                    let filename = download::saved_filename(&build_info, Some(&id), &opts);
+                    // Serialized up front, the build info is consumed by the download
+                    let metadata = write_metadata
+                        .then(|| download::metadata(&id, listed.as_ref(), &build_info));
+                    processed += build_info.size;
+                    // Downloading a bad build was already asked about, that's the one decision
+                    let confirm = confirm && !listed.as_ref().is_some_and(Build::is_bad);
+                    let http = client.http_client();
+                    let result =
+                        match download::download_build(http, build_info, Some(&id), &opts, confirm)
+                            .await
+                        {
+                            Err(e) if download::is_url_expired(&e) => {
+                                opts.status(format!(
+                                    "Download URL of {id} expired, resolving a fresh one"
+                                ));
+                                // Already confirmed for the first URL
+                                match client
+                                    .get_build_url(product, platform.clone(), &version, build)
+                                    .await
+                                    .map_err(|e| build_info_error(e, &version, build, &platform))
+                                {
+                                    Ok(build_info) => {
+                                        download::download_build(
+                                            http,
+                                            build_info,
+                                            Some(&id),
+                                            &opts,
+                                            false,
+                                        )
+                                        .await
+                                    }
+                                    Err(e) => Err(e),
+                                }
+                            }
+                            result => result,
+                        };
+                    // Skipped, resumed and retried downloads count their whole size once
+                    if let Some(batch) = &opts.batch {
+                        batch.total.set_position(processed);
+                    }
+                    let result = match (result, metadata) {
+                        (Ok(()), Some(metadata)) => {
+                            download::write_metadata(&opts, &filename, &metadata)
+                        }
+                        (result, _) => result,
+                    };
+                    // Declining the download is Ok too, only a file in place is recorded
+                    let result = match (result, &mut last_builds) {
+                        (Ok(()), Some(last_builds)) if download::is_saved(&opts, &filename) => {
+                            last_builds.record(LastBuilds::key(product, &platform, &version), build)
+                        }
+                        (result, _) => result,
+                    };
+                    match result {
+                        Ok(()) => succeeded.push(format!("{version}.{build}")),
+                        Err(e) if continue_on_error => {
+                            report_failed(&version, build, e, &mut failed)
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                if let Some(batch) = &opts.batch {
+                    batch.total.finish_and_clear();
+                }
+            }
+            if many && !dry_run && !opts.summary_only {
+                print_batch_summary(&succeeded, &failed);
+                eprintln!("Finished {} of {total} builds", succeeded.len());
+            }
+            if !failed.is_empty() {
+                let failed: Vec<_> = failed.iter().map(|(build, _)| build.as_str()).collect();
+                bail!("Failed builds: {}", failed.join(", "));
             }
         }
         Commands::List {
             include_daily_builds,
             version,
+            with_size,
+            all_platforms,
+            group_by_build,
+            format,
+            template,
+            sort,
+            reverse,
+            relative_dates,
+            channel,
+            since_build,
+            until_build,
+            python,
+            limit,
         } => {
-            let mut stdout = std::io::stdout().lock();
-            for (i, build) in client
-                .list_builds(
-                    args.product.into(),
-                    args.platform.into(),
+            if let Some(build) = version.and_then(|v| v.build()) {
+                eprintln!("[warning]: Ignoring build {build} in --version, listing all builds of the version");
+            }
+            let version = match version {
+                Some(version) => Some(resolve_version(&client, product, &platform, version).await?),
+                None => None,
+            };
+            let keep = |b: &Build| {
+                channel.matches(b)
+                    && b.is_in_build_range(since_build, until_build)
+                    && python
+                        .as_ref()
+                        .is_none_or(|python| b.python_version().as_ref() == Some(python))
+            };
+            let limit = list::effective_limit(limit, include_daily_builds, format);
+            let all_platforms = all_platforms || group_by_build;
+            if format == OutputFormat::Ndjson
+                && !group_by_build
+                && template.is_none()
+                && sort.is_none()
+                && !reverse
+            {
+                // Streamed in the order SideFX sends them, so the first N are printed
+                let (shown, hidden) = (Cell::new(0), Cell::new(0));
+                let keep = |b: &Build| {
+                    if !keep(b) {
+                        return false;
+                    }
+                    if limit.is_some_and(|limit| shown.get() >= limit) {
+                        hidden.set(hidden.get() + 1);
+                        return false;
+                    }
+                    shown.set(shown.get() + 1);
+                    true
+                };
+                let platforms = if all_platforms {
+                    Platform::ALL.to_vec()
+                } else {
+                    vec![platform]
+                };
+                return list::stream_ndjson(
+                    &client,
+                    product,
+                    platforms,
                     version,
                     !include_daily_builds,
+                    keep,
+                    with_size,
                 )
                 .await
-                .context("Error encountered when trying to list available builds")?
-                .into_iter()
-                .enumerate()
-            {
-                let status = if build.status == "bad" {
-                    std::borrow::Cow::Owned(build.status.color(AnsiColors::Red).to_string())
-                } else {
-                    std::borrow::Cow::Borrowed(build.status.as_str())
-                };
-                writeln!(
-                    stdout,
-                    "{i:>2}. Date: {}, Platform: {}, Version: {}.{}, Status: {}, Release: {}",
-                    build.date, build.platform, build.version, build.build, status, build.release
-                )?;
+                .inspect(|_| list::print_hidden_note(hidden.get()));
+            }
+            let mut builds = if all_platforms {
+                client
+                    .list_builds_all_platforms(product, version, !include_daily_builds)
+                    .await
+            } else {
+                client
+                    .list_builds(product, platform.clone(), version, !include_daily_builds)
+                    .await
+            }
+            .context("Error encountered when trying to list available builds")?;
+            builds.retain(keep);
+            sort.unwrap_or(SortArg::Version).order().sort(&mut builds);
+            let color = !args.no_color && std::io::stdout().is_terminal();
+            if group_by_build {
+                let mut groups = list::group_by_build(builds);
+                let summary = (format == OutputFormat::Text)
+                    .then(|| list::summary(groups.iter().map(|group| &group.build)))
+                    .flatten();
+                let hidden = list::truncate(&mut groups, limit);
+                if reverse {
+                    groups.reverse();
+                }
+                list::print_groups(&groups, format, color, relative_dates)?;
+                list::print_summary(summary, color)?;
+                list::print_hidden_note(hidden);
+                return Ok(());
+            }
+            // Of everything that matched, also the builds --limit leaves out
+            let summary = (format == OutputFormat::Text && template.is_none())
+                .then(|| list::summary(&builds))
+                .flatten();
+            let hidden = list::truncate(&mut builds, limit);
+            if reverse {
+                builds.reverse();
+            }
+            if let Some(template) = &template {
+                list::print_template(&builds, template)?;
+                list::print_hidden_note(hidden);
+                return Ok(());
             }
+            let sizes = if with_size {
+                futures_util::future::join_all(builds.iter().map(|build| async {
+                    client
+                        .get_build_url_for(build)
+                        .await
+                        .ok()
+                        .map(|url| url.size)
+                }))
+                .await
+            } else {
+                Vec::new()
+            };
+            list::print_builds(&builds, &sizes, format, color, relative_dates)?;
+            list::print_summary(summary, color)?;
+            list::print_hidden_note(hidden);
+        }
+        Commands::DownloadUrl { .. }
+        | Commands::Info
+        | Commands::Products { .. }
+        | Commands::Doctor
+        | Commands::SelfTest { .. } => {
+            unreachable!("handled before authorization")
+        }
+        #[cfg(feature = "keyring")]
+        Commands::Login | Commands::Logout => unreachable!("handled before authorization"),
+        Commands::LatestVersion {
+            include_daily_builds,
+        } => {
+            let builds = client
+                .list_builds(
+                    product,
+                    platform.clone(),
+                    None::<String>,
+                    !include_daily_builds,
+                )
+                .await
+                .context("Error encountered when trying to list available builds")?;
+            let Some(latest) = builds.iter().max_by_key(|b| b.version_tuple()) else {
+                bail!("No builds found");
+            };
+            println!("{}", latest.version);
+        }
+        Commands::Diff {
+            version,
+            from_build,
+            to_build,
+            format,
+        } => {
+            let version = resolve_version(&client, product, &platform, version).await?;
+            let (from, to) = futures_util::future::try_join(
+                client.find_build(product, platform.clone(), &version, from_build),
+                client.find_build(product, platform.clone(), &version, to_build),
+            )
+            .await
+            .context("Error encountered when trying to look up the builds")?;
+            let (from, to) = match (from, to) {
+                (Some(from), Some(to)) => (from, to),
+                (from, to) => {
+                    let missing: Vec<_> = [(from, from_build), (to, to_build)]
+                        .into_iter()
+                        .filter(|(found, _)| found.is_none())
+                        .map(|(_, build)| format!("{version}.{build}"))
+                        .collect();
+                    bail!("Build not found for {platform}: {}", missing.join(", "));
+                }
+            };
+            let color = !args.no_color && std::io::stdout().is_terminal();
+            diff::print_diff(&from, &to, format, color)?;
+        }
+        Commands::Versions { format } => {
+            let versions = client
+                .list_versions(product, platform)
+                .await
+                .context("Error encountered when trying to list available builds")?;
+            list::print_versions(&versions, format)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_info(args: &Args) -> Result<()> {
+    let source = |value: &Option<String>, env: &str| match args.credential_source(value, env) {
+        Some(source) => format!("found ({source})"),
+        None => "missing".to_string(),
+    };
+    fn ago(secs: u64) -> String {
+        match secs {
+            0..=59 => format!("{secs}s"),
+            60..=3599 => format!("{}m", secs / 60),
+            _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
         }
     }
 
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    println!("User id: {}", source(&args.user_id, "SESI_USER_ID"));
+    println!(
+        "User secret: {}",
+        source(&args.user_secret, "SESI_USER_SECRET")
+    );
+    let config = ClientConfig {
+        cache_dir: args.cache_dir.clone(),
+        token_cache: !args.no_cache,
+        ..ClientConfig::default()
+    };
+    match config.token_cache_file() {
+        Some(path) => println!("Token cache file: {}", path.to_string_lossy()),
+        None if args.no_cache => println!("Token cache file: disabled (--no-cache)"),
+        None => println!("Token cache file: no cache directory on this system"),
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    match config.cached_token_expires_at() {
+        Some(expires_at) if expires_at > now => {
+            println!("Cached token: valid, expires in {}", ago(expires_at - now))
+        }
+        Some(expires_at) => println!("Cached token: expired {} ago", ago(now - expires_at)),
+        None => println!("Cached token: none"),
+    }
+    match Platform::current() {
+        Some(platform) => println!("Default platform: {platform}"),
+        None => println!("Default platform: none, this OS has no SideFX builds"),
+    }
+    match args.platform() {
+        Ok(platform) => println!("Selected platform: {platform}"),
+        Err(e) => println!("Selected platform: {e}"),
+    }
     Ok(())
 }
+
+/// The `major.minor` of `version` as the API expects it. A major version alone is resolved to
+/// its newest minor version with builds, e.g. 19 to 19.5.
+async fn resolve_version(
+    client: &SesiClient,
+    product: Product,
+    platform: &Platform,
+    version: VersionArg,
+) -> Result<String> {
+    let major = match version {
+        VersionArg::Exact(version) => return Ok(version.without_build().to_string()),
+        VersionArg::Major(major) => major,
+    };
+    let versions = client
+        .list_versions(product, platform.clone())
+        .await
+        .context("Error encountered when trying to list available versions")?;
+    let Some(newest) = versions
+        .iter()
+        .filter_map(|version| version.parse::<Version>().ok())
+        .filter(|version| version.major == major)
+        .max()
+    else {
+        bail!("No {product} {major}.x versions found for {platform}");
+    };
+    let newest = newest.without_build().to_string();
+    eprintln!("Resolved version {major} to {newest}");
+    Ok(newest)
+}
+
+/// Context for a failed `get_build_url`, naming the build if it doesn't exist.
+fn build_info_error(e: ApiError, version: &str, build: u64, platform: &Platform) -> anyhow::Error {
+    let context = match e.kind() {
+        Kind::NotFound => format!("Build {version}.{build} not found for {platform}"),
+        _ => "Error encountered while trying to get build info".to_string(),
+    };
+    anyhow::Error::new(e).context(context)
+}
+
+/// Print the error of one build of a batch and remember it for the summary.
+fn report_failed(
+    version: &str,
+    build: u64,
+    e: anyhow::Error,
+    failed: &mut Vec<(String, anyhow::Error)>,
+) {
+    eprintln!("{}", format!("[error]: {version}.{build}: {e:#}").red());
+    failed.push((format!("{version}.{build}"), e));
+}
+
+/// What `get --dry-run` prints: an array for several targets and the object for a single one,
+/// `None` if none of them resolved.
+fn dry_run_json(mut summaries: Vec<serde_json::Value>, many: bool) -> Option<serde_json::Value> {
+    match many {
+        _ if summaries.is_empty() => None,
+        true => Some(summaries.into()),
+        false => summaries.pop(),
+    }
+}
+
+/// Table of every build of a batch with the reason of each failure.
+fn print_batch_summary(succeeded: &[String], failed: &[(String, anyhow::Error)]) {
+    let width = succeeded
+        .iter()
+        .chain(failed.iter().map(|(build, _)| build))
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("Build".len());
+    eprintln!("{:<width$}  Result", "Build");
+    for build in succeeded {
+        eprintln!("{build:<width$}  {}", "ok".green());
+    }
+    for (build, e) in failed {
+        eprintln!("{build:<width$}  {}: {e:#}", "failed".red());
+    }
+}
+
+/// Warn about a build SideFX marked "bad" and ask whether to download it anyway.
+/// Without prompts there is nobody to ask, so it's refused unless `allow_bad` is set.
+fn confirm_bad_build(build: &Build, auto_confirm: bool, allow_bad: bool) -> Result<bool> {
+    eprintln!(
+        "{}",
+        format!(
+            "[warning]: Build {} is marked \"bad\" by SideFX",
+            build.full_version()
+        )
+        .color(AnsiColors::Red)
+    );
+    if auto_confirm {
+        if !allow_bad {
+            bail!("Refusing to download a bad build without confirmation, pass --allow-bad to override");
+        }
+        return Ok(true);
+    }
+    Ok(download::ask(
+        Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Download it anyway?")
+            .default(false),
+    )?
+    .unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn dry_run_json_of_unresolved_targets() {
+        assert_eq!(dry_run_json(Vec::new(), false), None);
+        assert_eq!(dry_run_json(Vec::new(), true), None);
+        let summary = json!({"build": 805});
+        assert_eq!(
+            dry_run_json(vec![summary.clone()], false),
+            Some(summary.clone())
+        );
+        assert_eq!(
+            dry_run_json(vec![summary.clone()], true),
+            Some(json!([summary]))
+        );
+    }
+}
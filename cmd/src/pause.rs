@@ -0,0 +1,86 @@
+//! Pausing a download with SIGUSR1 and resuming it with SIGUSR2, e.g. `kill -USR1 <pid>`.
+//! Windows has no such signals, so downloads there can't be paused.
+
+use indicatif::ProgressBar;
+use tokio::sync::watch;
+
+/// Whether the download is paused: set by SIGUSR1 and cleared by SIGUSR2, installing the
+/// handlers on first use.
+#[cfg(unix)]
+pub fn flag() -> Option<watch::Receiver<bool>> {
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    static PAUSED: std::sync::OnceLock<Option<watch::Receiver<bool>>> = std::sync::OnceLock::new();
+    /// Write end of the pipe the handler passes the signals through.
+    static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    extern "C" fn handle(signal: libc::c_int) {
+        let paused = u8::from(signal == libc::SIGUSR1);
+        // SAFETY: writing a byte to a pipe is async-signal-safe, unlike updating the channel.
+        unsafe {
+            libc::write(
+                PIPE.load(Ordering::Relaxed),
+                (&paused as *const u8).cast(),
+                1,
+            );
+        }
+    }
+
+    PAUSED
+        .get_or_init(|| {
+            let mut fds = [0; 2];
+            // SAFETY: `fds` has room for both ends of the pipe.
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return None;
+            }
+            let [read, write] = fds;
+            PIPE.store(write, Ordering::Relaxed);
+            // SAFETY: the read end was just opened and isn't used anywhere else.
+            let mut signals = unsafe { std::fs::File::from_raw_fd(read) };
+            let (sender, receiver) = watch::channel(false);
+            std::thread::spawn(move || {
+                let mut paused = [0];
+                while signals.read_exact(&mut paused).is_ok() {
+                    sender.send_replace(paused[0] == 1);
+                }
+            });
+            let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            // SAFETY: the handler only writes to the pipe.
+            unsafe {
+                libc::signal(libc::SIGUSR1, handler);
+                libc::signal(libc::SIGUSR2, handler);
+            }
+            Some(receiver)
+        })
+        .clone()
+}
+
+#[cfg(not(unix))]
+pub fn flag() -> Option<watch::Receiver<bool>> {
+    None
+}
+
+/// Show on the progress bar, or on stderr without one, whenever the download is paused or
+/// resumed. Runs until aborted.
+pub async fn report(mut paused: watch::Receiver<bool>, bar: Option<ProgressBar>, message: String) {
+    let mut was_paused = false;
+    while paused.changed().await.is_ok() {
+        let is_paused = *paused.borrow_and_update();
+        if is_paused == was_paused {
+            continue;
+        }
+        was_paused = is_paused;
+        let status = if is_paused {
+            format!("{message} (paused, send SIGUSR2 to resume)")
+        } else {
+            message.clone()
+        };
+        match &bar {
+            Some(bar) => bar.set_message(status),
+            None if is_paused => eprintln!("{status}"),
+            None => eprintln!("{message} (resumed)"),
+        }
+    }
+}
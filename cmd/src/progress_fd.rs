@@ -0,0 +1,102 @@
+//! `--progress-fd`: progress events for programs wrapping houdl, which can't parse the rendered
+//! progress bar. One JSON object per line, at most 10 per second plus one for the last byte:
+//!
+//! `{"bps":5242880.0,"downloaded":1048576,"filename":"houdini-20.0.506-linux_x86_64_gcc11.2.tar.gz","total":2147483648}`
+//!
+//! `bps` is the average speed since the download started, in bytes per second.
+
+use anyhow::{bail, Result};
+use serde_json::json;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct ProgressEvents {
+    filename: String,
+    total: u64,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+struct State {
+    out: File,
+    downloaded: u64,
+    last: Option<Instant>,
+}
+
+impl ProgressEvents {
+    pub fn new(fd: i32, filename: &str, total: u64) -> Result<ProgressEvents> {
+        Ok(ProgressEvents {
+            filename: filename.to_string(),
+            total,
+            started: Instant::now(),
+            state: Mutex::new(State {
+                out: open(fd)?,
+                downloaded: 0,
+                last: None,
+            }),
+        })
+    }
+
+    /// Count `bytes` more downloaded, writing an event if the last one is old enough. A reader
+    /// that went away doesn't fail the download, the events are just lost.
+    pub fn inc(&self, bytes: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.downloaded += bytes;
+        let now = Instant::now();
+        let due = state.last.is_none_or(|last| now - last >= INTERVAL);
+        // Without a total, the last byte can't be recognized
+        if !due && (self.total == 0 || state.downloaded < self.total) {
+            return;
+        }
+        state.last = Some(now);
+        let elapsed = (now - self.started).as_secs_f64();
+        let event = json!({
+            "filename": self.filename,
+            "downloaded": state.downloaded,
+            "total": self.total,
+            "bps": if elapsed > 0.0 { state.downloaded as f64 / elapsed } else { 0.0 },
+        });
+        let mut line = event.to_string();
+        line.push('\n');
+        let _ = state.out.write_all(line.as_bytes());
+    }
+}
+
+/// A duplicate of `fd`, so the events of every download in a batch go to the same descriptor
+/// and dropping one doesn't close it.
+#[cfg(unix)]
+fn open(fd: i32) -> Result<File> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: dup only reads the descriptor table; the result is checked before use.
+    let dup = unsafe { libc::dup(fd) };
+    if dup < 0 {
+        bail!(
+            "Could not use file descriptor {fd} for progress events: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    // SAFETY: `dup` is a freshly duplicated descriptor nothing else owns.
+    Ok(unsafe { File::from_raw_fd(dup) })
+}
+
+/// Without descriptors to inherit, only stdout and stderr are supported.
+#[cfg(windows)]
+fn open(fd: i32) -> Result<File> {
+    use std::os::windows::io::AsHandle;
+
+    let handle = match fd {
+        1 => std::io::stdout().as_handle().try_clone_to_owned()?,
+        2 => std::io::stderr().as_handle().try_clone_to_owned()?,
+        _ => {
+            bail!("Only file descriptors 1 and 2 can be used for progress events on this platform")
+        }
+    };
+    Ok(File::from(handle))
+}
@@ -0,0 +1,110 @@
+//! `houdl self-test`: download a generated file from a server on localhost the same way `get`
+//! downloads a build, to check that streaming, hashing and verification work in this build of
+//! the tool. Needs neither credentials nor a connection to SideFX, so it also runs in CI.
+
+use crate::download::{self, DownloadOptions};
+use anyhow::{bail, Context, Result};
+use houdini_downloader_api::BuildUrl;
+use owo_colors::OwoColorize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+/// Not a multiple of the buffer or chunk sizes, so the last partial chunk is exercised too.
+const SIZE: usize = 3 * 1024 * 1024 + 7;
+/// md5 of [`fixture`], computed independently of this tool.
+const MD5: &str = "35bb59964e60f3085da9f9d04d484b87";
+
+fn fixture() -> Vec<u8> {
+    (0..SIZE).map(|i| ((i * 31 + 7) % 251) as u8).collect()
+}
+
+pub async fn run(silent: bool) -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").context("Could not start the test server")?;
+    let addr = listener.local_addr()?;
+    let data = Arc::new(fixture());
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let data = data.clone();
+            std::thread::spawn(move || serve(stream, &data));
+        }
+    });
+    let dir = std::env::temp_dir().join(format!("houdl-self-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Could not create {}", dir.to_string_lossy()))?;
+    // A proxy from the environment has no business with localhost
+    let http = reqwest::Client::builder().no_proxy().build()?;
+    let result = async {
+        for connections in [1, 4] {
+            let url = BuildUrl {
+                download_url: format!("http://{addr}/self-test.bin"),
+                filename: "self-test.bin".to_string(),
+                hash: MD5.to_string(),
+                size: SIZE as u64,
+            };
+            let opts = DownloadOptions {
+                output_dir: dir.clone(),
+                no_progress: silent,
+                overwrite: true,
+                connections,
+                ..DownloadOptions::default()
+            };
+            download::download_build(&http, url, None, &opts, false)
+                .await
+                .with_context(|| format!("Self-test failed with {connections} connection(s)"))?;
+            let written = std::fs::metadata(dir.join("self-test.bin"))?.len();
+            if written != SIZE as u64 {
+                bail!("Self-test failed: {written} bytes written instead of {SIZE}");
+            }
+            eprintln!(
+                "{} Self-test passed with {connections} connection(s)",
+                "✔".green()
+            );
+        }
+        Ok(())
+    }
+    .await;
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Answer one request for the fixture, honoring a `Range` header.
+fn serve(mut stream: TcpStream, data: &[u8]) {
+    let mut range = None;
+    let mut reader = BufReader::new(&stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("range") {
+                range = value.trim().strip_prefix("bytes=").and_then(|range| {
+                    let (start, end) = range.split_once('-')?;
+                    let start: usize = start.parse().ok()?;
+                    let end = match end {
+                        "" => data.len() - 1,
+                        end => end.parse::<usize>().ok()?.min(data.len() - 1),
+                    };
+                    (start <= end).then_some((start, end))
+                });
+            }
+        }
+    }
+    let (status, body, content_range) = match range {
+        Some((start, end)) => (
+            "206 Partial Content",
+            &data[start..=end],
+            format!("Content-Range: bytes {start}-{end}/{}\r\n", data.len()),
+        ),
+        None => ("200 OK", data, String::new()),
+    };
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\n{content_range}\
+        Accept-Ranges: bytes\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let _ = stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body));
+}
@@ -0,0 +1,95 @@
+//! `list --template`: one line per build from a format string like `{version}.{build} {status}`.
+//! `{{` and `}}` stand for literal braces.
+
+use houdini_downloader_api::Build;
+
+/// The placeholders a template can use.
+const FIELDS: [&str; 9] = [
+    "build",
+    "date",
+    "full_version",
+    "platform",
+    "product",
+    "python",
+    "release",
+    "status",
+    "version",
+];
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(String),
+    Field(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct Template(Vec<Token>);
+
+impl Template {
+    /// Parse `template`, rejecting unknown placeholders and unmatched braces.
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or("unterminated { in template")?;
+                    let name = &rest[..end];
+                    let field = FIELDS.iter().find(|field| **field == name).ok_or_else(|| {
+                        format!(
+                            "unknown placeholder {{{name}}}, expected one of: {}",
+                            FIELDS.join(", ")
+                        )
+                    })?;
+                    if !literal.is_empty() {
+                        tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(Token::Field(field));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched } in template, write }} for a literal one".into()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        Ok(Template(tokens))
+    }
+
+    pub fn render(&self, build: &Build) -> String {
+        let mut line = String::new();
+        for token in &self.0 {
+            match token {
+                Token::Literal(literal) => line.push_str(literal),
+                Token::Field(field) => line.push_str(&field_value(build, field)),
+            }
+        }
+        line
+    }
+}
+
+fn field_value(build: &Build, field: &str) -> String {
+    match field {
+        "build" => build.build.to_string(),
+        "date" => build.date.clone(),
+        "full_version" => build.full_version(),
+        "platform" => build.platform.clone(),
+        "product" => build.product.to_string(),
+        "python" => build.python_version().unwrap_or_default(),
+        "release" => build.release.clone(),
+        "status" => build.status.clone(),
+        "version" => build.version.clone(),
+        _ => unreachable!("placeholders are checked when parsing"),
+    }
+}